@@ -0,0 +1,330 @@
+//! Events of the minter following the NEP-297 format
+//! <https://github.com/near/NEPs/blob/master/specs/Standards/EventsFormat.md>
+//!
+//! Every event is logged as `EVENT_JSON:{"standard":"loterra_minter","version":"1.0.0",
+//! "event":"<name>","data":[...]}`, the payload fields are only extended in new versions.
+
+use crate::{DutchAuction, PackContractStatus, PauseScope, Role, SoftCap};
+use near_sdk::json_types::{Base58CryptoHash, U128};
+use near_sdk::serde::Serialize;
+use near_sdk::{env, serde_json, AccountId, PublicKey, StorageUsage, Timestamp};
+
+const EVENT_STANDARD: &str = "loterra_minter";
+const EVENT_VERSION: &str = "1.0.0";
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+#[serde(tag = "event", content = "data")]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum MinterEvent<'a> {
+    WhitelistAdd(Vec<WhitelistAddData<'a>>),
+    WhitelistRemove(Vec<AccountData<'a>>),
+    WhitelistUpdate(Vec<WhitelistAddData<'a>>),
+    TierSet(Vec<TierData<'a>>),
+    TierRemove(Vec<TierNameData<'a>>),
+    TierMemberAdd(Vec<TierMemberData<'a>>),
+    TierMemberRemove(Vec<AccountData<'a>>),
+    MerkleRootSet(Vec<MerkleRootData>),
+    VoucherKeySet(Vec<VoucherKeyData<'a>>),
+    StorageDeposit(Vec<StorageData<'a>>),
+    StorageWithdraw(Vec<StorageData<'a>>),
+    PurchaseStarted(Vec<PurchaseData<'a>>),
+    MintSucceeded(Vec<PurchaseData<'a>>),
+    MintFailed(Vec<PurchaseData<'a>>),
+    SupplyChange(Vec<SupplyData>),
+    PhaseChange(Vec<PhaseData>),
+    SaleEndChange(Vec<SaleEndData>),
+    AdminCollect(Vec<CollectData<'a>>),
+    AdminWithdrawNear(Vec<NearWithdrawData<'a>>),
+    PayeesSet(Vec<PayeeData<'a>>),
+    Distribute(Vec<PayoutData<'a>>),
+    PayoutClaim(Vec<PayoutData<'a>>),
+    Pause(Vec<PauseData<'a>>),
+    Unpause(Vec<PauseData<'a>>),
+    AuctionClose(Vec<AuctionCloseData>),
+    RebateClaim(Vec<RebateData<'a>>),
+    SoftCapSet(Vec<SoftCapData<'a>>),
+    SaleCancel(Vec<SaleCancelData>),
+    RefundClaim(Vec<RefundData<'a>>),
+    ReservedMint(Vec<ReservedMintData<'a>>),
+    MintStorageSet(Vec<MintStorageData>),
+    DutchAuctionSet(Vec<DutchAuctionData<'a>>),
+    PackBurnSet(Vec<PackBurnData>),
+    ReservedSupplySet(Vec<ReservedSupplyData>),
+    OwnerPropose(Vec<OwnerData<'a>>),
+    OwnershipTransfer(Vec<OwnerData<'a>>),
+    RoleGrant(Vec<RoleData<'a>>),
+    RoleRevoke(Vec<RoleData<'a>>),
+    NearMintingPriceSet(Vec<NearMintingPriceData>),
+    PaymentTokenAdd(Vec<PaymentTokenData<'a>>),
+    PaymentTokenEnabledSet(Vec<PaymentTokenEnabledData<'a>>),
+    PackContractRedeploy(Vec<PackContractData<'a>>),
+    PackContractDeploy(Vec<PackContractData<'a>>),
+    MigratedDepositsSet(Vec<MigratedDepositsData>),
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub(crate) struct WhitelistAddData<'a> {
+    pub account_id: &'a AccountId,
+    pub minting_start: Timestamp,
+    pub minting_price: U128,
+    pub minting_limit: u8,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub(crate) struct AccountData<'a> {
+    pub account_id: &'a AccountId,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub(crate) struct TierData<'a> {
+    pub tier: &'a str,
+    pub minting_start: Timestamp,
+    pub minting_end: Timestamp,
+    pub minting_price: U128,
+    pub minting_limit: u8,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub(crate) struct TierNameData<'a> {
+    pub tier: &'a str,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub(crate) struct TierMemberData<'a> {
+    pub account_id: &'a AccountId,
+    pub tier: &'a str,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub(crate) struct MerkleRootData {
+    pub merkle_root: Option<Base58CryptoHash>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub(crate) struct VoucherKeyData<'a> {
+    pub public_key: Option<&'a PublicKey>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub(crate) struct StorageData<'a> {
+    pub account_id: &'a AccountId,
+    pub amount: U128,
+    pub balance: U128,
+}
+
+/// Purchase of NFT packs, payment_token is None when paid in NEAR
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub(crate) struct PurchaseData<'a> {
+    pub buyer_id: &'a AccountId,
+    pub token_ids: &'a [String],
+    pub payment_token: Option<&'a AccountId>,
+    pub amount: U128,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub(crate) struct SupplyData {
+    pub supply: u16,
+    pub pending: u16,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub(crate) struct PhaseData {
+    pub private_sale_start: Timestamp,
+    pub public_sale_start: Timestamp,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub(crate) struct SaleEndData {
+    pub public_sale_end: Option<Timestamp>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub(crate) struct CollectData<'a> {
+    pub token_account_id: &'a AccountId,
+    pub receiver_id: &'a AccountId,
+    pub amount: U128,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub(crate) struct NearWithdrawData<'a> {
+    pub receiver_id: &'a AccountId,
+    pub amount: U128,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub(crate) struct PayeeData<'a> {
+    pub account_id: &'a AccountId,
+    pub share_bps: u16,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub(crate) struct PayoutData<'a> {
+    pub account_id: &'a AccountId,
+    pub token_account_id: &'a AccountId,
+    pub amount: U128,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub(crate) struct PauseData<'a> {
+    pub scope: &'a PauseScope,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub(crate) struct AuctionCloseData {
+    pub clearing_price: U128,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub(crate) struct RebateData<'a> {
+    pub account_id: &'a AccountId,
+    pub payment_token: &'a AccountId,
+    pub amount: U128,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub(crate) struct RefundData<'a> {
+    pub account_id: &'a AccountId,
+    pub payment_token: Option<&'a AccountId>,
+    pub amount: U128,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub(crate) struct SoftCapData<'a> {
+    pub soft_cap: Option<&'a SoftCap>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub(crate) struct SaleCancelData {
+    pub packs_sold: u16,
+    pub sale_proceeds: U128,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub(crate) struct ReservedMintData<'a> {
+    pub receiver_id: &'a AccountId,
+    pub token_ids: &'a [String],
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub(crate) struct MintStorageData {
+    pub mint_storage_cost: U128,
+    pub mint_storage_usage: StorageUsage,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub(crate) struct DutchAuctionData<'a> {
+    pub dutch_auction: Option<&'a DutchAuction>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub(crate) struct PackBurnData {
+    pub pack_burn: bool,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub(crate) struct ReservedSupplyData {
+    pub reserved_supply: u16,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub(crate) struct OwnerData<'a> {
+    pub owner_id: &'a AccountId,
+    pub new_owner_id: &'a AccountId,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub(crate) struct RoleData<'a> {
+    pub account_id: &'a AccountId,
+    pub role: Role,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub(crate) struct NearMintingPriceData {
+    pub near_minting_price: Option<U128>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub(crate) struct PaymentTokenData<'a> {
+    pub token_account_id: &'a AccountId,
+    pub decimals: u8,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub(crate) struct PaymentTokenEnabledData<'a> {
+    pub token_account_id: &'a AccountId,
+    pub enabled: bool,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub(crate) struct PackContractData<'a> {
+    pub nft_pack_contract: &'a AccountId,
+    pub status: PackContractStatus,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub(crate) struct MigratedDepositsData {
+    pub total_storage_deposits: U128,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+struct EventLog<'a> {
+    standard: &'static str,
+    version: &'static str,
+    #[serde(flatten)]
+    event: &'a MinterEvent<'a>,
+}
+
+impl MinterEvent<'_> {
+    pub(crate) fn to_json_event_string(&self) -> String {
+        format!(
+            "EVENT_JSON:{}",
+            serde_json::to_string(&EventLog {
+                standard: EVENT_STANDARD,
+                version: EVENT_VERSION,
+                event: self,
+            })
+            .unwrap()
+        )
+    }
+
+    /// Log the event to the host
+    pub(crate) fn emit(self) {
+        env::log_str(&self.to_json_event_string());
+    }
+}
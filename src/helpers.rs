@@ -1,79 +1,136 @@
-use crate::serde_json::Value;
+use crate::DEFAULT_GAS;
 use near_contract_standards::non_fungible_token::metadata::TokenMetadata;
-use near_sdk::json_types::U128;
-use near_sdk::serde_json::json;
-use near_sdk::{env, require, AccountId, Gas};
+use near_sdk::json_types::{Base58CryptoHash, U128};
+use near_sdk::serde_json::{json, Value};
+use near_sdk::{env, AccountId, CryptoHash, Gas, NearToken, Promise};
+use std::convert::TryInto;
 
-const MINT_STORAGE_COST: u128 = 5870000000000000000000;
-const DEFAULT_GAS: u64 = 5_000_000_000_000;
+/// Metadata of a minted NFT pack, the packs are only identified by their token id
+pub(crate) fn pack_token_metadata() -> TokenMetadata {
+    TokenMetadata {
+        title: None,
+        description: None,
+        media: None,
+        media_hash: None,
+        copies: None,
+        issued_at: None,
+        expires_at: None,
+        starts_at: None,
+        updated_at: None,
+        extra: None,
+        reference: None,
+        reference_hash: None,
+    }
+}
+
+/*
+   Every nft_mint call attaches its own mint_storage_cost, no refund_id is set so the NFT
+   contract refunds the unused storage deposit to the minter
+*/
 pub(crate) fn promise_mint_pack(
     nft_pack_contract: AccountId,
-    nft_pack_supply: u16,
-    receiver_id: AccountId,
+    token_ids: &[String],
+    receiver_id: &AccountId,
     token_metadata: TokenMetadata,
-    mint_limit: u16,
-    current_account: AccountId,
-    storage_deposit: U128,
-    // amount_cost: U128,
-) -> u128 {
-    let storage_mint = u128::from(mint_limit) * MINT_STORAGE_COST;
-    println!("{}, {}", storage_deposit.0, storage_mint);
-    require!(
-        storage_deposit.0 >= storage_mint,
-        format!(
-            "Minimum required for storage deposit of {} Yocto Near",
-            storage_mint
-        )
-    );
-
-    let promise_id = env::promise_batch_create(&nft_pack_contract);
+    mint_storage_cost: u128,
+    callback_method: &str,
+    callback_args: Value,
+) -> Promise {
+    // All the nft_mint calls are batched in a single receipt on the NFT pack contract
+    let mut promise = Promise::new(nft_pack_contract);
 
-    let mut n = 0;
-    while n < mint_limit {
-        let token_id = nft_pack_supply
-            .checked_sub(n.checked_add(1).unwrap())
-            .unwrap()
-            .to_string();
-        let mut arguments = json!({
+    for token_id in token_ids {
+        let arguments = json!({
             "token_id": token_id,
             "receiver_id": receiver_id,
             "token_metadata": token_metadata
         });
 
-        if mint_limit - n == 1 {
-            arguments["refund_id"] = Value::String(receiver_id.clone().to_string());
-        }
-
-        env::promise_batch_action_function_call(
-            promise_id,
-            "nft_mint",
-            arguments.to_string().as_bytes(),
-            storage_mint,
-            Gas::from(DEFAULT_GAS),
+        promise = promise.function_call(
+            "nft_mint".to_string(),
+            arguments.to_string().as_bytes().to_vec(),
+            NearToken::from_yoctonear(mint_storage_cost),
+            Gas::from_gas(DEFAULT_GAS),
         );
-
-        n += 1;
     }
 
-    let callback_promise_id = env::promise_batch_then(
-        promise_id,       // postpone until a DataReceipt associated with promise_id is received
-        &current_account, // the recipient of this ActionReceipt (&self)
-    );
     /*
-       Just for info if refund not work just add on argument and uncomment the mint_result params
-       message &json!({ "reduce_from_supply": mint_limit, "receiver_id": receiver_id, "from":
-       env::predecessor_account_id(), "refund_amount": amount_cost })
+       The callback rolls back the purchase if the batch fails, the amount_cost is then
+       returned to ft_on_transfer as unused amount so the FT contract refunds the sender
+       or transferred back to the sender when paid in NEAR
     */
-    env::promise_batch_action_function_call(
-        callback_promise_id, // associate the function call with callback_promise_id
-        "mint_result",       // the function call will be a callback function
-        &json!({ "reduce_from_supply": mint_limit })
-            .to_string()
-            .as_bytes(), // method arguments
-        0,                   // amount of yoctoNEAR to attach
-        Gas::from(DEFAULT_GAS), // gas to attach
+    let callback = Promise::new(env::current_account_id()).function_call(
+        callback_method.to_string(),
+        callback_args.to_string().as_bytes().to_vec(),
+        NearToken::from_yoctonear(0),
+        Gas::from_gas(DEFAULT_GAS),
+    );
+
+    promise.then(callback)
+}
+
+/*
+   The NFT pack contract burns the packs only if they are still owned by the buyer, all the
+   nft_burn calls are batched in a single receipt so no pack is burned if one fails
+*/
+pub(crate) fn promise_burn_packs(
+    nft_pack_contract: AccountId,
+    token_ids: &[String],
+    owner_id: &AccountId,
+    callback_args: Value,
+) -> Promise {
+    let mut promise = Promise::new(nft_pack_contract);
+
+    for token_id in token_ids {
+        let arguments = json!({
+            "token_id": token_id,
+            "owner_id": owner_id
+        });
+
+        promise = promise.function_call(
+            "nft_burn".to_string(),
+            arguments.to_string().as_bytes().to_vec(),
+            NearToken::from_yoctonear(1),
+            Gas::from_gas(DEFAULT_GAS),
+        );
+    }
+
+    let callback = Promise::new(env::current_account_id()).function_call(
+        "on_refund_packs_burned".to_string(),
+        callback_args.to_string().as_bytes().to_vec(),
+        NearToken::from_yoctonear(0),
+        Gas::from_gas(3 * DEFAULT_GAS),
     );
 
-    env::promise_return(callback_promise_id);
-    storage_mint
+    promise.then(callback)
+}
+
+/// Merkle whitelist leaf, sha256 of "account_id:minting_limit:minting_price"
+pub(crate) fn merkle_leaf(
+    account_id: &AccountId,
+    minting_limit: u8,
+    minting_price: U128,
+) -> CryptoHash {
+    env::sha256(format!("{}:{}:{}", account_id, minting_limit, minting_price.0).as_bytes())
+        .try_into()
+        .unwrap()
+}
+
+/// Verify the proof from the leaf to the root, the pairs are hashed sorted so the proof
+/// does not need the position of the nodes
+pub(crate) fn verify_merkle_proof(
+    leaf: CryptoHash,
+    proof: &[Base58CryptoHash],
+    root: Base58CryptoHash,
+) -> bool {
+    let computed = proof.iter().fold(leaf, |node, sibling| {
+        let sibling = CryptoHash::from(*sibling);
+        let pair = if node <= sibling {
+            [node, sibling].concat()
+        } else {
+            [sibling, node].concat()
+        };
+        env::sha256(&pair).try_into().unwrap()
+    });
+    computed == CryptoHash::from(root)
 }
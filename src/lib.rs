@@ -1,47 +1,150 @@
+mod events;
 mod helpers;
+mod upgrade;
 
-use crate::helpers::promise_mint_pack;
+use crate::events::{
+    AccountData, AuctionCloseData, CollectData, DutchAuctionData, MerkleRootData,
+    MigratedDepositsData, MintStorageData, MinterEvent, NearMintingPriceData, NearWithdrawData,
+    OwnerData, PackBurnData, PackContractData, PauseData, PayeeData, PaymentTokenData,
+    PaymentTokenEnabledData, PayoutData, PhaseData, PurchaseData, RebateData, RefundData,
+    ReservedMintData, ReservedSupplyData, RoleData, SaleCancelData, SaleEndData, SoftCapData,
+    StorageData, SupplyData, TierData, TierMemberData, TierNameData, VoucherKeyData,
+    WhitelistAddData,
+};
+use crate::helpers::{
+    merkle_leaf, pack_token_metadata, promise_burn_packs, promise_mint_pack, verify_merkle_proof,
+};
 use near_contract_standards::fungible_token::receiver::FungibleTokenReceiver;
-use near_contract_standards::non_fungible_token::metadata::{NFTContractMetadata, TokenMetadata};
+use near_contract_standards::non_fungible_token::metadata::NFTContractMetadata;
+use near_contract_standards::storage_management::{
+    StorageBalance, StorageBalanceBounds, StorageManagement,
+};
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
-use near_sdk::collections::{LookupMap, UnorderedMap};
-use near_sdk::json_types::U128;
+use near_sdk::collections::{LookupMap, LookupSet, UnorderedMap, Vector};
+use near_sdk::json_types::{Base58CryptoHash, Base64VecU8, U128};
 use near_sdk::serde::{Deserialize, Serialize};
 use near_sdk::serde_json::json;
 use near_sdk::{
-    assert_one_yocto, env, log, near_bindgen, require, serde_json, AccountId, Gas, PanicOnDefault,
-    Promise, PromiseOrValue, PromiseResult, Timestamp,
+    assert_one_yocto, env, log, near_bindgen, require, serde_json, AccountId, CurveType, Gas,
+    NearToken, PanicOnDefault, Promise, PromiseOrValue, PromiseResult, PublicKey, StorageUsage,
+    Timestamp,
 };
+use std::convert::TryInto;
 
 const CODE: &[u8] =
     include_bytes!("../../NFT/target/wasm32-unknown-unknown/release/non_fungible_token.wasm");
 const DEFAULT_GAS: u64 = 5_000_000_000_000;
-const MINT_STORAGE_COST: u128 = 5870000000000000000000;
+/*
+   Default storage deposit attached to every nft_mint and upper bound of the storage used by
+   a minted NFT pack, both can be updated by the owner once measured on the NFT pack contract.
+   A mint measured 587 bytes, the owner id is stored twice so the bound adds 2 * 64 bytes for
+   the longest account id. The deposit keeps a margin over the bound to be refunded
+*/
+const MINT_STORAGE_COST: u128 = 8_000_000_000_000_000_000_000;
+const MINT_STORAGE_USAGE: StorageUsage = 720;
+/*
+   Minting prices are expressed with 6 decimals (USDC/USDT unit) and converted to the
+   decimals of the payment token, e.g. DAI uses 18 decimals
+*/
+const PRICE_DECIMALS: u8 = 6;
+// Highest decimals of a payment token, the price conversion overflows u128 above 38 decimals
+const MAX_TOKEN_DECIMALS: u8 = 24;
 /*
    IMPORTANT: Reduce amount for mainnet
 */
 const MIN_DEPOSIT_CREATING_ACCOUNT: u128 = 5_000_000_000_000_000_000_000_000;
+const MAX_SYMBOL_LEN: usize = 16;
+const MAX_TIER_NAME_LEN: usize = 32;
+const TOTAL_SHARE_BPS: u16 = 10_000;
+/*
+   Gas kept to finish a whitelist batch, the remaining items are skipped and can be sent
+   again in a new transaction
+*/
+const BATCH_GAS_RESERVE: u64 = 10_000_000_000_000;
 
 #[near_bindgen]
 #[derive(BorshDeserialize, BorshSerialize, PanicOnDefault)]
+#[borsh(crate = "near_sdk::borsh")]
 pub struct Minter {
     whitelist: UnorderedMap<AccountId, WhitelistState>, // Create whitelist storage key address => WhitelistState value
+    tiers: UnorderedMap<String, WhitelistTier>, // Create tiers storage key name => WhitelistTier value
+    tier_members: LookupMap<AccountId, String>, // Create a storage key address => tier name value
+    merkle_root: Option<Base58CryptoHash>, // Root of the Merkle whitelist, None disable the proofs
+    voucher_public_key: Option<PublicKey>, // Key signing the vouchers off chain, None disable the vouchers
+    used_voucher_nonces: LookupSet<u64>,   // Nonces of the vouchers already used
     minting_price: U128,
     admin: AccountId,
-    usdc_account_id: AccountId,
-    usdt_account_id: AccountId,
-    // dai_account_id: AccountId,
+    pending_admin: Option<AccountId>, // Proposed owner waiting to accept the ownership
+    roles: UnorderedMap<AccountId, Vec<Role>>, // Create roles storage key address => granted roles value
+    payment_tokens: UnorderedMap<AccountId, PaymentToken>, // Accepted NEP-141 contract => PaymentToken value
+    near_minting_price: Option<U128>, // Minting price in yoctoNEAR, None disable the NEAR payment
+    dutch_auction: Option<DutchAuction>, // Decreasing public sale price, None use the minting_price
+    auction_purchases: LookupMap<(AccountId, AccountId), Purchase>, // Create a storage key (buyer, payment token) => Purchase value
+    auction_last_price: Option<U128>, // Lowest auction price paid by a successful purchase
+    clearing_price: Option<U128>,     // Final auction price, set once the auction is closed
+    auction_totals: LookupMap<AccountId, Purchase>, // Create a storage key payment token => auction packs and amount paid value
+    payees: Vec<Payee>,                             // Revenue split of the proceeds
+    proceeds: LookupMap<AccountId, U128>, // Create a storage key payment token => undistributed proceeds value
+    payouts: LookupMap<(AccountId, AccountId), Payout>, // Create a storage key (payee, payment token) => Payout value
+    payment_totals: LookupMap<AccountId, PaymentTotals>, // Create a storage key payment token => PaymentTotals value
+    purchases: LookupMap<(AccountId, AccountId), Purchase>, // Create a storage key (buyer, payment token) => refundable Purchase value
+    near_purchases: LookupMap<AccountId, Purchase>, // Create a storage key buyer => refundable Purchase paid in NEAR value
+    soft_cap: Option<SoftCap>, // Minimum sale to release the proceeds, None never refund the buyers
+    sale_cancelled: bool,      // Cancelled by the owner, the buyers claim a refund
+    packs_sold: u16,           // NFT packs minted by the buyers
+    sale_proceeds: u128,       // Amount paid in the payment tokens with PRICE_DECIMALS
+    near_withdrawn: u128,      // NEAR withdrawn by the admin
+    pack_burn: bool,           // The NFT packs are burned when refunded
     private_sale_start: u64,
     public_sale_start: u64,
-    switch_off: bool,
-    minted: LookupMap<AccountId, u16>, // Create a storage key address => minted_amount value
+    public_sale_end: Option<Timestamp>, // End of the sale, None keep the public sale open
+    switch_off: bool,                   // Pause all minting
+    public_sale_paused: bool,           // Pause only the public sale
+    minted: LookupMap<AccountId, u16>,  // Create a storage key address => minted_amount value
     storage_deposits: LookupMap<AccountId, U128>,
+    total_storage_deposits: u128, // Sum of the storage deposits, never swept by the admin
+    migrated_deposits: bool,      // Total of the v1 storage deposits not set yet
+    pending_near_payments: u128,  // NEAR paid for mints waiting for the NFT pack contract result
+    mint_storage_cost: U128,      // Storage deposit attached to every nft_mint
+    mint_storage_usage: StorageUsage, // Upper bound of the bytes used by a minted NFT pack on the NFT pack contract
     nft_pack_contract: AccountId,
-    nft_pack_supply: u16, // Available mint and decrease on every mint
+    nft_pack_supply: u16,  // Available mint and decrease on every reserved mint
+    nft_pack_pending: u16, // Reserved mint waiting for the NFT pack contract result
+    token_id_cursor: u16,  // Token ids below the cursor have never been reserved
+    released_token_ids: Vector<u16>, // Token ids released by failed mints, reserved first
+    reserved_supply: u16,  // Supply kept for the team and partners, minted by the owner only
+    reserved_minted: u16,  // NFT pack minted from the reserved supply
+    pack_metadata: NFTContractMetadata,
+    pack_contract_status: PackContractStatus, // Sales are only open once the NFT pack contract is deployed
+}
+
+/// Roles granted by the owner, the owner is allowed to call every role method
+#[derive(
+    BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, Debug, PartialEq,
+)]
+#[borsh(crate = "near_sdk::borsh")]
+#[serde(crate = "near_sdk::serde")]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    WhitelistManager,
+    Treasurer,
+    Pauser,
+    Upgrader,
+}
+
+#[derive(
+    BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Copy, Debug, PartialEq,
+)]
+#[borsh(crate = "near_sdk::borsh")]
+#[serde(crate = "near_sdk::serde")]
+pub enum PackContractStatus {
+    Pending,
+    Deployed,
+    Failed,
 }
 
-#[near_bindgen]
 #[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Debug, PartialEq)]
+#[borsh(crate = "near_sdk::borsh")]
 #[serde(crate = "near_sdk::serde")]
 pub struct WhitelistState {
     minting_start: Timestamp,
@@ -49,84 +152,331 @@ pub struct WhitelistState {
     minting_limit: u8,
 }
 
+/// Whitelist state of an account sent to the whitelist batch methods
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct WhitelistEntry {
+    account_id: AccountId,
+    minting_start: Timestamp,
+    minting_price: U128,
+    minting_limit: u8,
+}
+
+impl WhitelistEntry {
+    fn event_data(&self) -> WhitelistAddData<'_> {
+        WhitelistAddData {
+            account_id: &self.account_id,
+            minting_start: self.minting_start,
+            minting_price: self.minting_price,
+            minting_limit: self.minting_limit,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+#[serde(rename_all = "snake_case")]
+pub enum BatchStatus {
+    Added,
+    Updated,
+    Removed,
+    AlreadyExist,
+    NotFound,
+    InvalidMintingStart,
+    Skipped, // Not processed to stay within the gas limit
+}
+
+/// Result of a whitelist batch for an account
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct BatchResult {
+    account_id: AccountId,
+    status: BatchStatus,
+}
+
+/// Private sale terms shared by every member of the tier, the tier terms take precedence
+/// over the whitelist state of the account
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Debug, PartialEq)]
+#[borsh(crate = "near_sdk::borsh")]
+#[serde(crate = "near_sdk::serde")]
+pub struct WhitelistTier {
+    minting_start: Timestamp,
+    minting_end: Timestamp,
+    minting_price: U128,
+    minting_limit: u8,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Debug, PartialEq)]
+#[borsh(crate = "near_sdk::borsh")]
+#[serde(crate = "near_sdk::serde")]
+pub struct PaymentToken {
+    decimals: u8,
+    enabled: bool,
+    paused: bool,
+}
+
+/// Payee of the revenue split, the shares of the payees sum to TOTAL_SHARE_BPS
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[borsh(crate = "near_sdk::borsh")]
+#[serde(crate = "near_sdk::serde")]
+pub struct Payee {
+    account_id: AccountId,
+    share_bps: u16,
+}
+
+/// Proceeds distributed to a payee in a payment token
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Debug, PartialEq)]
+#[borsh(crate = "near_sdk::borsh")]
+#[serde(crate = "near_sdk::serde")]
+pub struct Payout {
+    claimable: U128,
+    paid: U128,
+}
+
+/// Amounts of a payment token received for purchases, refunded to the buyers and withdrawn
+/// by the payees and the treasurer, the balance is received - refunded - withdrawn
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Debug, PartialEq)]
+#[borsh(crate = "near_sdk::borsh")]
+#[serde(crate = "near_sdk::serde")]
+pub struct PaymentTotals {
+    received: U128,
+    refunded: U128,
+    withdrawn: U128,
+}
+
+impl Default for PaymentTotals {
+    fn default() -> Self {
+        Self {
+            received: U128::from(0),
+            refunded: U128::from(0),
+            withdrawn: U128::from(0),
+        }
+    }
+}
+
+impl PaymentTotals {
+    fn balance(&self) -> u128 {
+        self.received.0 - self.refunded.0 - self.withdrawn.0
+    }
+}
+
+/// Current phase of the sale, the private sale ends when the public sale starts
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub enum SalePhase {
+    NotStarted,
+    Private,
+    Public,
+    Ended,
+    SoldOut,
+    Paused,
+    Cancelled,
+}
+
+/// Minimum sale reached by the public sale end, measured in NFT packs sold or in FT proceeds
+/// with PRICE_DECIMALS. The buyers claim a refund if the soft cap is missed
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[borsh(crate = "near_sdk::borsh")]
+#[serde(crate = "near_sdk::serde")]
+#[serde(rename_all = "snake_case")]
+pub enum SoftCap {
+    Packs(u16),
+    Proceeds(U128),
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct SoftCapStatus {
+    soft_cap: Option<SoftCap>,
+    packs_sold: u16,
+    sale_proceeds: U128,
+    reached: bool,
+    refunds_open: bool, // The sale is cancelled or ended below the soft cap
+}
+
+/// Emergency stop scope, withdrawals and refunds keep working while paused
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[borsh(crate = "near_sdk::borsh")]
+#[serde(crate = "near_sdk::serde")]
+#[serde(rename_all = "snake_case")]
+pub enum PauseScope {
+    All,
+    PublicSale,
+    PaymentToken(AccountId),
+}
+
+/// Public sale price decreasing by price_step every step_interval from the public sale start
+/// until the floor_price, prices are expressed with PRICE_DECIMALS
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[borsh(crate = "near_sdk::borsh")]
+#[serde(crate = "near_sdk::serde")]
+pub struct DutchAuction {
+    start_price: U128,
+    floor_price: U128,
+    price_step: U128,
+    step_interval: u64, // Nanoseconds between two price decreases
+    rebate: bool,       // Buyers claim the difference with the clearing price once closed
+}
+
+/// Packs bought and the amount paid in the payment token
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Debug, Default, PartialEq)]
+#[borsh(crate = "near_sdk::borsh")]
+#[serde(crate = "near_sdk::serde")]
+pub struct Purchase {
+    packs: u16,
+    paid: U128,
+}
+
+impl DutchAuction {
+    /// Price after elapsed nanoseconds since the public sale start
+    fn price_at(&self, elapsed: u64) -> u128 {
+        let steps = u128::from(elapsed / self.step_interval);
+        self.start_price
+            .0
+            .saturating_sub(steps.saturating_mul(self.price_step.0))
+            .max(self.floor_price.0)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PublicSalePrice {
+    current_price: U128,
+    next_price: Option<U128>, // None when the price will not change anymore
+    next_price_at: Option<Timestamp>, // Timestamp of the next price decrease
+}
+
+impl PaymentToken {
+    /// A token with less than PRICE_DECIMALS can't pay every price, the conversion would
+    /// round the amount paid down
+    fn assert_decimals(decimals: u8) {
+        require!(
+            decimals >= PRICE_DECIMALS,
+            format!("Payment token decimals below {}", PRICE_DECIMALS)
+        );
+        require!(
+            decimals <= MAX_TOKEN_DECIMALS,
+            format!("Payment token decimals above {}", MAX_TOKEN_DECIMALS)
+        );
+    }
+
+    /// Convert a price with PRICE_DECIMALS to the amount of this token
+    fn to_token_amount(&self, price: u128) -> u128 {
+        price
+            .checked_mul(10u128.pow(u32::from(self.decimals - PRICE_DECIMALS)))
+            .unwrap()
+    }
+
+    /// Convert an amount of this token to a price with PRICE_DECIMALS
+    fn to_price(&self, amount: u128) -> u128 {
+        amount / 10u128.pow(u32::from(self.decimals - PRICE_DECIMALS))
+    }
+}
+
+/// Whitelist terms of the buyer proven against the Merkle root, the private sale is open
+/// for the buyer from the private sale start
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct MerkleProof {
+    minting_limit: u8,
+    minting_price: U128,
+    proof: Vec<Base58CryptoHash>,
+}
+
+/// Private sale allocation issued off chain, the voucher is used once for up to max_amount
+/// NFT packs at the price until the expiry
+#[derive(BorshSerialize, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[borsh(crate = "near_sdk::borsh")]
+#[serde(crate = "near_sdk::serde")]
+pub struct Voucher {
+    account_id: AccountId,
+    max_amount: u8,
+    price: U128,
+    expiry: Timestamp,
+    nonce: u64,
+}
+
+/// Voucher and the ed25519 signature by the voucher key of the Borsh serialization of
+/// (minter account id, voucher)
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(crate = "near_sdk::serde")]
+pub struct SignedVoucher {
+    voucher: Voucher,
+    signature: Base64VecU8,
+}
+
 #[derive(Serialize, Deserialize)]
 #[serde(crate = "near_sdk::serde")]
 #[serde(untagged)]
 enum TokenReceiverMessage {
     /// Mint an NFT, amount will be used to verify the deposit.
-    Mint { mint_amount: u16 },
+    /// The Merkle proof or the voucher is only required for accounts not in the whitelist
+    Mint {
+        mint_amount: u16,
+        merkle_proof: Option<MerkleProof>,
+        voucher: Option<SignedVoucher>,
+    },
 }
 
 #[near_bindgen]
 impl Minter {
-    /// Instantiate the contract
-    #[init]
-    pub fn new(
-        minting_price: U128,
-        usdc_account_id: AccountId,
-        usdt_account_id: AccountId,
-        //dai_account_id: AccountId,
-        private_sale_start: u64,
-        public_sale_start: u64,
-        nft_pack_supply: u16,
-    ) -> Self {
-        log!(format!("creator: {}", env::signer_account_id()));
-        require!(
-            private_sale_start < public_sale_start,
-            "The private sale should start before the public sale"
-        );
-        /*
-            Allows our contract to deploy the NFT pack contract as admin more info for
-            dev help https://www.near-sdk.io/promises/deploy-contract
-        */
-        let subaccount_id =
-            AccountId::new_unchecked(format!("nft_pack9.{}", env::current_account_id()));
-        let current_accout = env::current_account_id();
+    /// Owner propose a new owner, the ownership is transferred once accepted
+    pub fn propose_owner(&mut self, new_owner_id: AccountId) {
+        self.assert_owner();
+        MinterEvent::OwnerPropose(vec![OwnerData {
+            owner_id: &self.admin,
+            new_owner_id: &new_owner_id,
+        }])
+        .emit();
+        self.pending_admin = Some(new_owner_id);
+    }
 
-        let metadata = NFTContractMetadata {
-            spec: "nft-1.0.0".to_string(),
-            name: "test".to_string(),
-            symbol: "PACK".to_string(),
-            icon: None,
-            base_uri: None,
-            reference: None,
-            reference_hash: None,
-        };
+    /// Proposed owner accept the ownership
+    pub fn accept_ownership(&mut self) {
+        let account_id = env::predecessor_account_id();
+        require!(
+            self.pending_admin.as_ref() == Some(&account_id),
+            "Only the proposed owner can accept the ownership"
+        );
+        MinterEvent::OwnershipTransfer(vec![OwnerData {
+            owner_id: &self.admin,
+            new_owner_id: &account_id,
+        }])
+        .emit();
+        self.admin = account_id;
+        self.pending_admin = None;
+    }
 
-        Promise::new(subaccount_id.clone())
-            .create_account()
-            .transfer(MIN_DEPOSIT_CREATING_ACCOUNT)
-            .add_full_access_key(env::signer_account_pk())
-            .deploy_contract(CODE.to_vec())
-            .function_call(
-                "new".to_string(),
-                json!({
-                    "owner_id":  current_accout,
-                    "metadata": metadata
-                })
-                .to_string()
-                .as_bytes()
-                .to_vec(),
-                0,
-                Gas::from(DEFAULT_GAS),
-            );
+    /// Owner grant a role to an account
+    pub fn grant_role(&mut self, account_id: AccountId, role: Role) {
+        self.assert_owner();
+        let mut roles = self.roles.get(&account_id).unwrap_or_default();
+        require!(!roles.contains(&role), "Role already granted");
+        roles.push(role);
+        self.roles.insert(&account_id, &roles);
+        MinterEvent::RoleGrant(vec![RoleData {
+            account_id: &account_id,
+            role,
+        }])
+        .emit();
+    }
 
-        Self {
-            whitelist: UnorderedMap::new(b"s"),
-            minting_price,
-            admin: env::signer_account_id(),
-            usdc_account_id,
-            usdt_account_id,
-            // dai_account_id,
-            private_sale_start,
-            public_sale_start,
-            // Not used should we use a switch off??
-            switch_off: false,
-            minted: LookupMap::new(b"m"),
-            storage_deposits: LookupMap::new(b"d"),
-            nft_pack_contract: subaccount_id,
-            nft_pack_supply,
+    /// Owner revoke a role from an account
+    pub fn revoke_role(&mut self, account_id: AccountId, role: Role) {
+        self.assert_owner();
+        let mut roles = self.roles.get(&account_id).unwrap_or_default();
+        require!(roles.contains(&role), "Role not granted");
+        roles.retain(|granted| *granted != role);
+        if roles.is_empty() {
+            self.roles.remove(&account_id);
+        } else {
+            self.roles.insert(&account_id, &roles);
         }
+        MinterEvent::RoleRevoke(vec![RoleData {
+            account_id: &account_id,
+            role,
+        }])
+        .emit();
     }
 
     /// Admin add account id to whitelist
@@ -137,13 +487,13 @@ impl Minter {
         minting_price: U128,
         minting_limit: u8,
     ) {
-        require!(env::signer_account_id() == self.admin, "Owner's method");
+        self.assert_role(Role::WhitelistManager);
         require!(
-            env::is_valid_account_id(&account_id.as_bytes()),
+            env::is_valid_account_id(account_id.as_bytes()),
             "Not a valid account id"
         );
         require!(
-            self.whitelist.get(&account_id.clone().into()).is_none(),
+            self.whitelist.get(&account_id).is_none(),
             "Account already exist"
         );
         require!(
@@ -152,7 +502,7 @@ impl Minter {
         );
 
         self.whitelist.insert(
-            &account_id.clone().into(),
+            &account_id,
             &WhitelistState {
                 minting_start,
                 minting_price,
@@ -160,650 +510,5629 @@ impl Minter {
             },
         );
 
-        log!(format!("Whitelist account {}", account_id));
+        MinterEvent::WhitelistAdd(vec![WhitelistAddData {
+            account_id: &account_id,
+            minting_start,
+            minting_price,
+            minting_limit,
+        }])
+        .emit();
     }
 
     /// Admin delete account from whitelist
     pub fn delete_whitelist(&mut self, account_id: AccountId) {
-        require!(env::signer_account_id() == self.admin, "Owner's method");
+        self.assert_role(Role::WhitelistManager);
         require!(
-            env::is_valid_account_id(&account_id.as_bytes()),
+            env::is_valid_account_id(account_id.as_bytes()),
             "Not a valid account id"
         );
         require!(
-            self.whitelist.get(&account_id.clone().into()).is_some(),
+            self.whitelist.get(&account_id).is_some(),
             "Account not found"
         );
 
-        self.whitelist.remove(&account_id.clone().into());
-        log!(format!("Delete whitelist account {}", account_id));
+        self.whitelist.remove(&account_id);
+        MinterEvent::WhitelistRemove(vec![AccountData {
+            account_id: &account_id,
+        }])
+        .emit();
     }
 
-    /// Near deposit storage, used as fee for minting NFT
-    #[payable]
-    pub fn storage_deposit(&mut self, account: Option<AccountId>) {
-        require!(
-            env::attached_deposit() >= MINT_STORAGE_COST,
-            format!("Requires minimum deposit of {}", MINT_STORAGE_COST)
-        );
-        log!("Deposited {}YoctoNear", env::attached_deposit());
-        let account_id = account.unwrap_or(env::signer_account_id());
-        let deposit = U128::from(env::attached_deposit());
+    /// Admin add accounts to the whitelist, accounts already whitelisted are left unchanged
+    pub fn add_whitelist_batch(&mut self, entries: Vec<WhitelistEntry>) -> Vec<BatchResult> {
+        self.assert_role(Role::WhitelistManager);
+        self.internal_whitelist_batch(entries, false)
+    }
 
-        if self
-            .storage_deposits
-            .contains_key(&account_id.clone().into())
-        {
-            let balance = self
-                .storage_deposits
-                .get(&account_id.clone().into())
-                .unwrap_or(U128::from(0));
-            self.storage_deposits.insert(
-                &account_id.clone().into(),
-                &U128::from(deposit.0.checked_add(balance.0).unwrap()),
-            );
+    /// Admin add accounts to the whitelist or update the limit, price and start of the
+    /// accounts already whitelisted
+    pub fn update_whitelist(&mut self, entries: Vec<WhitelistEntry>) -> Vec<BatchResult> {
+        self.assert_role(Role::WhitelistManager);
+        self.internal_whitelist_batch(entries, true)
+    }
 
-            log!(
-                "Balance {}YoctoNear",
-                deposit.0.checked_add(balance.0).unwrap()
-            );
-        } else {
-            self.storage_deposits.insert(&account_id.into(), &deposit);
+    /// Admin delete accounts from the whitelist
+    pub fn remove_whitelist_batch(&mut self, account_ids: Vec<AccountId>) -> Vec<BatchResult> {
+        self.assert_role(Role::WhitelistManager);
+        let mut removed = vec![];
+        let results = account_ids
+            .into_iter()
+            .map(|account_id| {
+                let status = if Self::internal_batch_out_of_gas() {
+                    BatchStatus::Skipped
+                } else if self.whitelist.remove(&account_id).is_some() {
+                    removed.push(account_id.clone());
+                    BatchStatus::Removed
+                } else {
+                    BatchStatus::NotFound
+                };
+                BatchResult { account_id, status }
+            })
+            .collect();
+
+        if !removed.is_empty() {
+            MinterEvent::WhitelistRemove(
+                removed
+                    .iter()
+                    .map(|account_id| AccountData { account_id })
+                    .collect(),
+            )
+            .emit();
         }
+        results
     }
 
-    /// Withdraw all storage deposited
-    #[payable]
-    pub fn storage_withdraw_all(&mut self) -> Promise {
-        assert_one_yocto();
-        let account = env::signer_account_id();
+    /// Admin create or update a tier, the new terms apply to every member of the tier
+    pub fn set_tier(
+        &mut self,
+        tier: String,
+        minting_start: Timestamp,
+        minting_end: Timestamp,
+        minting_price: U128,
+        minting_limit: u8,
+    ) {
+        self.assert_role(Role::WhitelistManager);
         require!(
-            self.storage_deposits.contains_key(&account.clone().into()),
-            "No account found"
+            !tier.is_empty() && tier.len() <= MAX_TIER_NAME_LEN,
+            format!("Tier name should be 1 to {} characters", MAX_TIER_NAME_LEN)
         );
-        let balance = self
-            .storage_deposits
-            .get(&account.clone().into())
-            .unwrap_or(U128::from(0));
-        require!(balance > U128::from(0), "Empty balance");
-        self.storage_deposits.remove(&account.clone().into());
-        log!(
-            "Withdraw all storage ({}YoctoNear to {})",
-            balance.0,
-            account
+        require!(
+            minting_start < minting_end,
+            "Minting start should be before the minting end"
+        );
+        self.tiers.insert(
+            &tier,
+            &WhitelistTier {
+                minting_start,
+                minting_end,
+                minting_price,
+                minting_limit,
+            },
         );
+        MinterEvent::TierSet(vec![TierData {
+            tier: &tier,
+            minting_start,
+            minting_end,
+            minting_price,
+            minting_limit,
+        }])
+        .emit();
+    }
 
-        Promise::new(account).transfer(balance.0)
+    /// Admin delete a tier, the members fall back to their whitelist state
+    pub fn delete_tier(&mut self, tier: String) {
+        self.assert_role(Role::WhitelistManager);
+        require!(self.tiers.remove(&tier).is_some(), "Tier not found");
+        MinterEvent::TierRemove(vec![TierNameData { tier: &tier }]).emit();
     }
 
-    /*
-       Allow admin to withdraw collected funds out of the Minter contract
-    */
-    /// Admin can withdraw collected funds
-    pub fn admin_collect(&self, from: AccountId, amount: U128) -> Promise {
-        let signer_account_id = env::signer_account_id();
-        require!(signer_account_id == self.admin, "Owner's method");
+    /// Admin assign accounts to a tier, an account belongs to a single tier
+    pub fn add_tier_members(&mut self, tier: String, account_ids: Vec<AccountId>) {
+        self.assert_role(Role::WhitelistManager);
+        require!(self.tiers.get(&tier).is_some(), "Tier not found");
+        for account_id in &account_ids {
+            self.tier_members.insert(account_id, &tier);
+        }
+        MinterEvent::TierMemberAdd(
+            account_ids
+                .iter()
+                .map(|account_id| TierMemberData {
+                    account_id,
+                    tier: &tier,
+                })
+                .collect(),
+        )
+        .emit();
+    }
 
-        Promise::new(from).function_call(
-            "ft_transfer".to_string(),
-            json!({
-            "receiver_id": signer_account_id,
-            "amount": amount
-            })
-            .to_string()
-            .as_bytes()
-            .to_vec(),
-            0,
-            Gas::from(DEFAULT_GAS),
+    /// Admin remove accounts from their tier
+    pub fn remove_tier_members(&mut self, account_ids: Vec<AccountId>) {
+        self.assert_role(Role::WhitelistManager);
+        for account_id in &account_ids {
+            require!(
+                self.tier_members.remove(account_id).is_some(),
+                format!("Account {} is not in a tier", account_id)
+            );
+        }
+        MinterEvent::TierMemberRemove(
+            account_ids
+                .iter()
+                .map(|account_id| AccountData { account_id })
+                .collect(),
         )
+        .emit();
     }
 
-    /// Queries
-    /// Get storage balance from account id
-    // Allow users to check their deposited amount
-    pub fn get_storage_balance_of(&self, account: AccountId) -> U128 {
+    /// Admin set the root of the Merkle whitelist, None disable the Merkle whitelist
+    pub fn set_merkle_root(&mut self, merkle_root: Option<Base58CryptoHash>) {
+        self.assert_role(Role::WhitelistManager);
+        self.merkle_root = merkle_root;
+        MinterEvent::MerkleRootSet(vec![MerkleRootData { merkle_root }]).emit();
+    }
+
+    /// Admin add an accepted NEP-141 payment token. The decimals of an added token can't
+    /// change as the purchases are recorded with them
+    pub fn add_payment_token(&mut self, token_account_id: AccountId, decimals: u8) {
+        self.assert_owner();
+        require!(
+            env::is_valid_account_id(token_account_id.as_bytes()),
+            "Not a valid account id"
+        );
+        PaymentToken::assert_decimals(decimals);
         require!(
-            self.storage_deposits.contains_key(&account.clone().into()),
-            "No account found"
+            self.payment_tokens.get(&token_account_id).is_none(),
+            "Payment token already added"
         );
+        self.payment_tokens.insert(
+            &token_account_id,
+            &PaymentToken {
+                decimals,
+                enabled: true,
+                paused: false,
+            },
+        );
+        MinterEvent::PaymentTokenAdd(vec![PaymentTokenData {
+            token_account_id: &token_account_id,
+            decimals,
+        }])
+        .emit();
+    }
 
-        self.storage_deposits
-            .get(&account.clone().into())
-            .unwrap_or(U128::from(0))
+    /// Admin enable or disable an accepted payment token
+    pub fn set_payment_token_enabled(&mut self, token_account_id: AccountId, enabled: bool) {
+        self.assert_owner();
+        let mut payment_token = self
+            .payment_tokens
+            .get(&token_account_id)
+            .expect("Payment token not found");
+        payment_token.enabled = enabled;
+        self.payment_tokens
+            .insert(&token_account_id, &payment_token);
+        MinterEvent::PaymentTokenEnabledSet(vec![PaymentTokenEnabledData {
+            token_account_id: &token_account_id,
+            enabled,
+        }])
+        .emit();
     }
 
-    /// Query get whitelist by pagination from index + limit
-    pub fn get_whitelist(&self, from_index: u64, limit: u64) -> Vec<(AccountId, WhitelistState)> {
-        let keys = self.whitelist.keys_as_vector();
-        let values = self.whitelist.values_as_vector();
-        (from_index..std::cmp::min(from_index + limit, self.whitelist.len()))
-            .map(|index| (keys.get(index).unwrap(), values.get(index).unwrap()))
-            .collect()
+    /// Owner set the storage deposit attached to every nft_mint and the upper bound of the
+    /// bytes used by a minted NFT pack, measured on the NFT pack contract with the longest
+    /// account id so the storage refund credited is never above the actual refund
+    pub fn set_mint_storage(&mut self, mint_storage_cost: U128, mint_storage_usage: StorageUsage) {
+        self.assert_owner();
+        require!(
+            mint_storage_cost.0
+                >= u128::from(mint_storage_usage) * env::storage_byte_cost().as_yoctonear(),
+            "The mint storage cost should cover the mint storage usage"
+        );
+        self.mint_storage_cost = mint_storage_cost;
+        self.mint_storage_usage = mint_storage_usage;
+        MinterEvent::MintStorageSet(vec![MintStorageData {
+            mint_storage_cost,
+            mint_storage_usage,
+        }])
+        .emit();
     }
 
-    /// Get minting info from account id
-    pub fn get_minting_of(&self, account: AccountId) -> u16 {
+    /// Owner set the reserved supply, the NFT packs are moved between the supply and the
+    /// reserved supply
+    pub fn set_reserved_supply(&mut self, reserved_supply: u16) {
+        self.assert_owner();
+        let total_supply = self
+            .nft_pack_supply
+            .checked_add(self.reserved_supply)
+            .unwrap();
+        require!(
+            reserved_supply <= total_supply,
+            format!("Supply limit reached. Left {} NFT pack", total_supply)
+        );
+        self.nft_pack_supply = total_supply - reserved_supply;
+        self.reserved_supply = reserved_supply;
+        MinterEvent::ReservedSupplySet(vec![ReservedSupplyData { reserved_supply }]).emit();
+        self.emit_supply_change();
+    }
+
+    /// Owner mint NFT packs from the reserved supply, no payment and no storage deposit of
+    /// the receiver are required. The owner attaches the storage of the NFT packs, the NEAR
+    /// held by the minter is owed to the buyers until the soft cap is reached
+    #[payable]
+    pub fn mint_reserved(&mut self, receiver_id: AccountId, mint_amount: u16) -> Promise {
+        self.assert_owner();
+        require!(
+            self.pack_contract_status == PackContractStatus::Deployed,
+            "NFT pack contract is not deployed"
+        );
+        require!(!self.switch_off, "Minting is paused");
+        require!(!self.sale_cancelled, "The sale is cancelled");
+        require!(mint_amount > 0);
+        require!(
+            self.reserved_supply >= mint_amount,
+            format!(
+                "Reserved supply limit reached. Left {} NFT pack",
+                self.reserved_supply
+            )
+        );
+        let storage_cost = u128::from(mint_amount) * self.mint_storage_cost.0;
+        let deposit = env::attached_deposit().as_yoctonear();
         require!(
-            self.minted.contains_key(&account.clone().into()),
-            "No account found"
+            deposit >= storage_cost,
+            format!("Requires minimum deposit of {} YoctoNear", storage_cost)
         );
+        let owner_id = env::predecessor_account_id();
+        if deposit > storage_cost {
+            Promise::new(owner_id.clone())
+                .transfer(NearToken::from_yoctonear(deposit - storage_cost));
+        }
+        self.reserved_supply -= mint_amount;
+        let token_ids = self.internal_take_token_ids(mint_amount);
+        self.emit_supply_change();
 
-        self.minted.get(&account.clone().into()).unwrap_or_default()
+        promise_mint_pack(
+            self.nft_pack_contract.clone(),
+            &token_ids,
+            &receiver_id,
+            pack_token_metadata(),
+            self.mint_storage_cost.0,
+            "on_reserved_minted",
+            json!({
+                "token_ids": token_ids,
+                "receiver_id": receiver_id,
+                "owner_id": owner_id,
+                "storage_cost": U128::from(storage_cost)
+            }),
+        )
     }
 
+    /// Callback of the reserved minting, the token ids go back to the reserved supply and
+    /// the storage deposit to the owner if the mint failed
     #[private]
-    pub fn mint_result(
+    pub fn on_reserved_minted(
         &mut self,
-        reduce_from_supply: u16,
-        // _receiver_id: AccountId,
-        // _from: AccountId,
-        // _refund_amount: U128,
+        token_ids: Vec<String>,
+        receiver_id: AccountId,
+        owner_id: AccountId,
+        storage_cost: U128,
     ) {
         require!(env::promise_results_count() == 1);
+        let mint_amount = token_ids.len() as u16;
+        self.nft_pack_pending = self.nft_pack_pending.checked_sub(mint_amount).unwrap();
         match env::promise_result(0) {
-            PromiseResult::NotReady => unreachable!(),
             PromiseResult::Successful(_) => {
-                log!(format!("Successfully minted {} pack", reduce_from_supply));
-                self.nft_pack_supply = self
-                    .nft_pack_supply
-                    .checked_sub(reduce_from_supply)
-                    .unwrap();
+                self.reserved_minted = self.reserved_minted.checked_add(mint_amount).unwrap();
+                MinterEvent::ReservedMint(vec![ReservedMintData {
+                    receiver_id: &receiver_id,
+                    token_ids: &token_ids,
+                }])
+                .emit();
             }
             PromiseResult::Failed => {
-                /*
-                   Highlighting we probably don't need to refund
-                   FT contract is already waiting a PromiseOrValue with a refund if fail
-                */
-                // log!("Refund because failed to mint NFT pack");
-                // // Probably we need to refund the user here
-                // Promise::new(from).function_call(
-                //     "ft_transfer_call".to_string(),
-                //     json!({
-                //         "receiver_id": receiver_id,
-                //         "amount": refund_amount
-                //     })
-                //     .to_string()
-                //     .as_bytes()
-                //     .to_vec(),
-                //     0,
-                //     Gas::from(DEFAULT_GAS),
-                // );
-
-                // OR panic
-                env::panic_str("Minting failed")
+                log!(format!("Reserved mint failed for {}", receiver_id));
+                self.internal_return_token_ids(&token_ids);
+                self.reserved_supply = self.reserved_supply.checked_add(mint_amount).unwrap();
+                Promise::new(owner_id).transfer(NearToken::from_yoctonear(storage_cost.0));
             }
         }
+        self.emit_supply_change();
     }
-}
-
-#[near_bindgen]
-impl FungibleTokenReceiver for Minter {
-    fn ft_on_transfer(
-        &mut self,
-        sender_id: AccountId,
-        amount: U128,
-        msg: String,
-    ) -> PromiseOrValue<U128> {
-        require!(
-            // self.dai_account_id == env::predecessor_account_id()
-            //     ||
-            self.usdc_account_id == env::predecessor_account_id()
-                || self.usdt_account_id == env::predecessor_account_id(),
-            "Only allowed NF contracts can call this message"
-        );
-
-        log!(
-            "in {} tokens from @{} ft_on_transfer, msg = {}",
-            amount.0,
-            sender_id.as_ref(),
-            msg
-        );
-
-        // Mint info start
-        let token_metadata = TokenMetadata {
-            title: None,
-            description: None,
-            media: None,
-            media_hash: None,
-            copies: None,
-            issued_at: None,
-            expires_at: None,
-            starts_at: None,
-            updated_at: None,
-            extra: None,
-            reference: None,
-            reference_hash: None,
-        };
-
-        let receiver_id = sender_id.clone();
-
-        if msg.is_empty() {
-            log!("Missing msg in ft_transfer_call");
-            PromiseOrValue::Value(amount)
-        } else {
-            let message = serde_json::from_str::<TokenReceiverMessage>(&msg)
-                .expect("Illegal msg in ft_transfer_call");
-            // Mint info end
-            match message {
-                TokenReceiverMessage::Mint { mint_amount } => {
-                    require!(mint_amount > 0);
-                    /*
-                       INFO: USDC & USDT are 6 decimals but DAI are 18 decimals.
-                    */
-                    // Verify the amount sent match with minting cost
-                    require!(
-                        amount == U128::from(self.minting_price.0 * u128::from(mint_amount)),
-                        format!(
-                            "Wrong amount sent, minting price {:?} DAI/USDC/USDT",
-                            self.minting_price.0 * u128::from(mint_amount)
-                        )
-                    );
 
-                    require!(
-                        self.nft_pack_supply >= mint_amount,
-                        format!(
-                            "Supply limit reached. Left {} NFT pack",
-                            self.nft_pack_supply
-                        )
-                    );
-                    let storage_deposit = self.storage_deposits.get(&sender_id.clone());
-                    require!(
-                        storage_deposit.is_some(),
-                        "Action required deposit Near for storage"
-                    );
+    /// Owner set the public key signing the vouchers, None disable the vouchers
+    pub fn set_voucher_public_key(&mut self, public_key: Option<PublicKey>) {
+        self.assert_owner();
+        if let Some(public_key) = &public_key {
+            require!(
+                public_key.curve_type() == CurveType::ED25519,
+                "Voucher key should be an ed25519 key"
+            );
+        }
+        MinterEvent::VoucherKeySet(vec![VoucherKeyData {
+            public_key: public_key.as_ref(),
+        }])
+        .emit();
+        self.voucher_public_key = public_key;
+    }
 
-                    match env::block_timestamp() {
-                        time if time >= self.public_sale_start => {
-                            // Save the Sender to minted storage and increment the amount already minted
-                            if self.minted.contains_key(&sender_id.clone().into()) {
-                                let amount_minted =
-                                    self.minted.get(&sender_id.clone().into()).unwrap();
-                                self.minted.insert(
-                                    &sender_id.clone().into(),
-                                    &amount_minted.checked_add(mint_amount).unwrap(),
-                                );
-                            } else {
-                                self.minted.insert(&sender_id.clone().into(), &mint_amount);
-                            }
+    /// Admin pause the minting for the scope
+    pub fn pause(&mut self, scope: PauseScope) {
+        self.assert_role(Role::Pauser);
+        self.internal_set_paused(&scope, true);
+        MinterEvent::Pause(vec![PauseData { scope: &scope }]).emit();
+    }
 
-                            // Mint the NFT pack and send it to the sender
-                            let used_storage_deposit = promise_mint_pack(
-                                self.nft_pack_contract.clone(),
-                                self.nft_pack_supply,
-                                receiver_id,
-                                token_metadata,
-                                mint_amount,
-                                env::current_account_id(),
-                                storage_deposit.unwrap_or(U128::from(0)),
-                                // amount,
-                            );
+    /// Admin unpause the minting for the scope
+    pub fn unpause(&mut self, scope: PauseScope) {
+        self.assert_role(Role::Pauser);
+        self.internal_set_paused(&scope, false);
+        MinterEvent::Unpause(vec![PauseData { scope: &scope }]).emit();
+    }
 
-                            self.storage_deposits.insert(
-                                &sender_id.clone().into(),
-                                &U128::from(
-                                    storage_deposit
-                                        .unwrap_or(U128::from(0))
-                                        .0
-                                        .checked_sub(used_storage_deposit)
-                                        .unwrap(),
-                                ),
-                            );
+    /// Owner reschedule the private sale, only before the private sale started
+    pub fn reschedule_private_sale(&mut self, private_sale_start: Timestamp) {
+        self.assert_owner();
+        require!(
+            env::block_timestamp() < self.private_sale_start,
+            "The private sale already started"
+        );
+        require!(
+            private_sale_start >= env::block_timestamp(),
+            "The private sale start should be greater than now"
+        );
+        require!(
+            private_sale_start < self.public_sale_start,
+            "The private sale should start before the public sale"
+        );
+        self.private_sale_start = private_sale_start;
+        self.emit_phase_change();
+    }
 
-                            PromiseOrValue::Value(U128::from(0))
-                        }
-                        time if time >= self.private_sale_start => {
-                            // Verify the sender is in the whitelist
-                            require!(
-                                self.whitelist.get(&sender_id.clone().into()).is_some(),
-                                "The address is not in the whitelist"
-                            );
-                            // Verify the sender have not reached the minting limit
-                            // Save the Sender to minted storage and increment the amount already minted
-                            let whitelist_user =
-                                self.whitelist.get(&sender_id.clone().into()).unwrap();
-                            if self.minted.contains_key(&sender_id.clone().into()) {
-                                let amount_minted =
-                                    self.minted.get(&sender_id.clone().into()).unwrap();
-                                require!(
-                                    u16::from(whitelist_user.minting_limit)
-                                        >= amount_minted.checked_add(mint_amount).unwrap(),
-                                    "Out of mint"
-                                );
-                                self.minted.insert(
-                                    &sender_id.clone().into(),
-                                    &amount_minted.checked_add(mint_amount).unwrap(),
-                                );
-                            } else {
-                                require!(
-                                    u16::from(whitelist_user.minting_limit) >= mint_amount,
-                                    format!(
-                                        "Whitelisted account only allowed to mint {} NFTs pack",
-                                        whitelist_user.minting_limit
-                                    )
-                                );
-                                self.minted.insert(&sender_id.clone().into(), &mint_amount);
-                            }
-                            // Mint the NFT pack and send it to the sender
-                            let used_storage_deposit = promise_mint_pack(
-                                self.nft_pack_contract.clone(),
-                                self.nft_pack_supply,
-                                receiver_id,
-                                token_metadata,
-                                mint_amount,
-                                env::current_account_id(),
-                                storage_deposit.unwrap_or(U128::from(0)),
-                                // amount,
-                            );
-                            self.storage_deposits.insert(
-                                &sender_id.clone().into(),
-                                &U128::from(
-                                    storage_deposit
-                                        .unwrap_or(U128::from(0))
-                                        .0
-                                        .checked_sub(used_storage_deposit)
-                                        .unwrap(),
-                                ),
-                            );
+    /// Owner reschedule the public sale, only before the public sale started
+    pub fn reschedule_public_sale(&mut self, public_sale_start: Timestamp) {
+        self.assert_owner();
+        require!(
+            env::block_timestamp() < self.public_sale_start,
+            "The public sale already started"
+        );
+        require!(
+            public_sale_start >= env::block_timestamp(),
+            "The public sale start should be greater than now"
+        );
+        require!(
+            self.private_sale_start < public_sale_start,
+            "The private sale should start before the public sale"
+        );
+        if let Some(public_sale_end) = self.public_sale_end {
+            require!(
+                public_sale_start < public_sale_end,
+                "The public sale should start before the sale end"
+            );
+        }
+        self.public_sale_start = public_sale_start;
+        self.emit_phase_change();
+    }
 
-                            PromiseOrValue::Value(U128::from(0))
-                        }
-                        _ => {
-                            log!("Sale have not started yet");
-                            PromiseOrValue::Value(amount)
-                        }
-                    }
-                }
+    /// Owner set the end of the sale, None keep the public sale open. Only before the sale ended,
+    /// the end can't be removed while a soft cap is set
+    pub fn set_public_sale_end(&mut self, public_sale_end: Option<Timestamp>) {
+        self.assert_owner();
+        require!(
+            self.internal_sale_phase() != SalePhase::Ended,
+            "The sale already ended"
+        );
+        match public_sale_end {
+            Some(public_sale_end) => require!(
+                public_sale_end > self.public_sale_start
+                    && public_sale_end >= env::block_timestamp(),
+                "The sale end should be after the public sale start and now"
+            ),
+            None => require!(
+                self.soft_cap.is_none(),
+                "The sale end is required by the soft cap"
+            ),
+        }
+        self.public_sale_end = public_sale_end;
+        MinterEvent::SaleEndChange(vec![SaleEndData { public_sale_end }]).emit();
+    }
+
+    /// Admin set the minting price in yoctoNEAR, None disable the NEAR payment.
+    /// The NEAR payment is not available with a proceeds soft cap, it only counts the FT paid
+    pub fn set_near_minting_price(&mut self, near_minting_price: Option<U128>) {
+        self.assert_owner();
+        if near_minting_price.is_some() {
+            require!(
+                !matches!(self.soft_cap, Some(SoftCap::Proceeds(_))),
+                "The NEAR payment is not counted in the proceeds soft cap"
+            );
+        }
+        MinterEvent::NearMintingPriceSet(vec![NearMintingPriceData { near_minting_price }]).emit();
+        self.near_minting_price = near_minting_price;
+    }
+
+    /// Admin set the Dutch auction of the public sale, None use the fixed minting price
+    pub fn set_dutch_auction(&mut self, dutch_auction: Option<DutchAuction>) {
+        self.assert_owner();
+        require!(
+            self.clearing_price.is_none(),
+            "The Dutch auction is already closed"
+        );
+        if let Some(auction) = &dutch_auction {
+            require!(
+                auction.start_price.0 >= auction.floor_price.0,
+                "The start price should not be lower than the floor price"
+            );
+            require!(
+                auction.price_step.0 > 0 && auction.step_interval > 0,
+                "The price step and step interval should be positive"
+            );
+        }
+        MinterEvent::DutchAuctionSet(vec![DutchAuctionData {
+            dutch_auction: dutch_auction.as_ref(),
+        }])
+        .emit();
+        self.dutch_auction = dutch_auction;
+    }
+
+    /// Admin close the Dutch auction and fix the clearing price to the lowest price paid,
+    /// the auction is also closed when the supply is sold out. Anyone can close it once the
+    /// sale end is passed so the rebates can't be locked. The pending mints have to be resolved
+    /// first, their price is part of the clearing price
+    pub fn close_auction(&mut self) {
+        let sale_ended = self
+            .public_sale_end
+            .is_some_and(|end| env::block_timestamp() >= end);
+        if !sale_ended {
+            self.assert_owner();
+        }
+        require!(
+            self.dutch_auction
+                .as_ref()
+                .is_some_and(|auction| auction.rebate),
+            "Dutch auction rebate is not enabled"
+        );
+        require!(
+            self.clearing_price.is_none(),
+            "The Dutch auction is already closed"
+        );
+        require!(
+            env::block_timestamp() >= self.public_sale_start,
+            "The Dutch auction has not started yet"
+        );
+        require!(
+            self.nft_pack_pending == 0,
+            "NFT pack mints are still pending"
+        );
+        self.internal_close_auction();
+    }
+
+    /// Claim the difference between the price paid and the clearing price, in the
+    /// token used to pay
+    pub fn claim_rebate(&mut self, payment_token: AccountId) -> Promise {
+        let account_id = env::predecessor_account_id();
+        let rebate = self.internal_rebate(&account_id, &payment_token);
+        require!(rebate > 0, "No rebate to claim");
+        let key = (account_id.clone(), payment_token.clone());
+        let purchase = self.auction_purchases.remove(&key).unwrap();
+        self.internal_update_totals(&payment_token, |totals| {
+            totals.refunded = U128::from(totals.refunded.0 + rebate)
+        });
+        self.internal_update_purchase(&key, |purchase| {
+            purchase.paid = U128::from(purchase.paid.0 - rebate)
+        });
+        MinterEvent::RebateClaim(vec![RebateData {
+            account_id: &account_id,
+            payment_token: &payment_token,
+            amount: U128::from(rebate),
+        }])
+        .emit();
+
+        Promise::new(payment_token.clone())
+            .function_call(
+                "ft_transfer".to_string(),
+                json!({
+                "receiver_id": account_id,
+                "amount": U128::from(rebate)
+                })
+                .to_string()
+                .as_bytes()
+                .to_vec(),
+                NearToken::from_yoctonear(1),
+                Gas::from_gas(DEFAULT_GAS),
+            )
+            .then(
+                Promise::new(env::current_account_id()).function_call(
+                    "on_rebate_claimed".to_string(),
+                    json!({
+                        "account_id": account_id,
+                        "payment_token": payment_token,
+                        "purchase": purchase
+                    })
+                    .to_string()
+                    .as_bytes()
+                    .to_vec(),
+                    NearToken::from_yoctonear(0),
+                    Gas::from_gas(DEFAULT_GAS),
+                ),
+            )
+    }
+
+    /// Callback of the rebate transfer, the purchase is restored if the transfer failed
+    #[private]
+    pub fn on_rebate_claimed(
+        &mut self,
+        account_id: AccountId,
+        payment_token: AccountId,
+        purchase: Purchase,
+    ) {
+        require!(env::promise_results_count() == 1);
+        if let PromiseResult::Failed = env::promise_result(0) {
+            log!(format!(
+                "Rebate transfer of {} failed for {}",
+                payment_token, account_id
+            ));
+            let key = (account_id.clone(), payment_token.clone());
+            self.auction_purchases.insert(&key, &purchase);
+            let rebate = self.internal_rebate(&account_id, &payment_token);
+            self.internal_update_totals(&payment_token, |totals| {
+                totals.refunded = U128::from(totals.refunded.0 - rebate)
+            });
+            self.internal_update_purchase(&key, |purchase| {
+                purchase.paid = U128::from(purchase.paid.0 + rebate)
+            });
+        }
+    }
+
+    /// Buy NFT pack paying in NEAR, the overpayment is refunded.
+    /// Whitelisted accounts pay the NEAR minting price during the private sale,
+    /// the public sale can't be paid in NEAR during a Dutch auction
+    #[payable]
+    pub fn nft_pack_buy(
+        &mut self,
+        mint_amount: u16,
+        merkle_proof: Option<MerkleProof>,
+        voucher: Option<SignedVoucher>,
+    ) -> Promise {
+        let near_minting_price = self
+            .near_minting_price
+            .expect("NEAR payment is not enabled");
+        let buyer_id = env::predecessor_account_id();
+        let (whitelist_user, voucher_nonce) =
+            self.internal_record_mint(&buyer_id, mint_amount, merkle_proof, voucher);
+        require!(
+            whitelist_user.is_some() || self.dutch_auction.is_none(),
+            "NEAR payment is not available during the Dutch auction"
+        );
+
+        // Verify the deposit cover the minting cost
+        let cost = near_minting_price.0 * u128::from(mint_amount);
+        let deposit = env::attached_deposit().as_yoctonear();
+        require!(
+            deposit >= cost,
+            format!("Requires minimum deposit of {} YoctoNear", cost)
+        );
+        if deposit > cost {
+            Promise::new(buyer_id.clone()).transfer(NearToken::from_yoctonear(deposit - cost));
+        }
+        self.pending_near_payments += cost;
+
+        self.internal_mint_pack(
+            buyer_id,
+            mint_amount,
+            U128::from(cost),
+            U128::from(0),
+            None,
+            None,
+            voucher_nonce,
+        )
+    }
+
+    /// Admin retry to deploy the NFT pack contract after a failed deployment
+    pub fn redeploy_pack_contract(&mut self) -> Promise {
+        self.assert_owner();
+        require!(
+            self.pack_contract_status == PackContractStatus::Failed,
+            "NFT pack contract deployment is not failed"
+        );
+        self.pack_contract_status = PackContractStatus::Pending;
+        MinterEvent::PackContractRedeploy(vec![PackContractData {
+            nft_pack_contract: &self.nft_pack_contract,
+            status: self.pack_contract_status,
+        }])
+        .emit();
+
+        self.internal_deploy_pack_contract()
+    }
+
+    /// Callback of the NFT pack contract deployment
+    #[private]
+    pub fn on_pack_contract_deployed(&mut self) {
+        require!(env::promise_results_count() == 1);
+        self.pack_contract_status = match env::promise_result(0) {
+            PromiseResult::Successful(_) => PackContractStatus::Deployed,
+            PromiseResult::Failed => PackContractStatus::Failed,
+        };
+        MinterEvent::PackContractDeploy(vec![PackContractData {
+            nft_pack_contract: &self.nft_pack_contract,
+            status: self.pack_contract_status,
+        }])
+        .emit();
+    }
+
+    /*
+       Allow admin to withdraw collected funds out of the Minter contract. Only the proceeds
+       not distributed to the payees can be collected, they never exceed the tracked balance
+    */
+    /// Admin can withdraw collected funds
+    pub fn admin_collect(&mut self, from: AccountId, amount: U128) -> Promise {
+        self.assert_role(Role::Treasurer);
+        self.assert_proceeds_unlocked();
+        let receiver_id = env::predecessor_account_id();
+        let proceeds = self.proceeds.get(&from).unwrap_or(U128::from(0));
+        let balance = self.payment_totals.get(&from).unwrap_or_default().balance();
+        let available = std::cmp::min(proceeds.0, balance);
+        require!(
+            amount.0 <= available,
+            format!(
+                "The amount is greater than the collectable balance {}",
+                available
+            )
+        );
+        self.proceeds
+            .insert(&from, &U128::from(proceeds.0 - amount.0));
+        self.internal_update_totals(&from, |totals| {
+            totals.withdrawn = U128::from(totals.withdrawn.0 + amount.0)
+        });
+        MinterEvent::AdminCollect(vec![CollectData {
+            token_account_id: &from,
+            receiver_id: &receiver_id,
+            amount,
+        }])
+        .emit();
+
+        Promise::new(from.clone())
+            .function_call(
+                "ft_transfer".to_string(),
+                json!({
+                "receiver_id": receiver_id,
+                "amount": amount
+                })
+                .to_string()
+                .as_bytes()
+                .to_vec(),
+                NearToken::from_yoctonear(1),
+                Gas::from_gas(DEFAULT_GAS),
+            )
+            .then(
+                Promise::new(env::current_account_id()).function_call(
+                    "on_admin_collected".to_string(),
+                    json!({
+                        "token_account_id": from,
+                        "amount": amount
+                    })
+                    .to_string()
+                    .as_bytes()
+                    .to_vec(),
+                    NearToken::from_yoctonear(0),
+                    Gas::from_gas(DEFAULT_GAS),
+                ),
+            )
+    }
+
+    /// Callback of the admin collect transfer, the proceeds are restored if the transfer failed
+    #[private]
+    pub fn on_admin_collected(&mut self, token_account_id: AccountId, amount: U128) {
+        require!(env::promise_results_count() == 1);
+        if let PromiseResult::Failed = env::promise_result(0) {
+            log!(format!(
+                "Collect transfer of {} {} failed",
+                amount.0, token_account_id
+            ));
+            self.internal_add_proceeds(&token_account_id, amount.0);
+            self.internal_update_totals(&token_account_id, |totals| {
+                totals.withdrawn = U128::from(totals.withdrawn.0 - amount.0)
+            });
+        }
+    }
+
+    /// Admin withdraw the NEAR collected once the soft cap is reached, the storage deposits,
+    /// the NEAR of the pending mints and the contract storage are never withdrawn
+    pub fn admin_withdraw_near(&mut self, amount: U128) -> Promise {
+        self.assert_role(Role::Treasurer);
+        self.assert_proceeds_unlocked();
+        let available = self.internal_withdrawable_near();
+        require!(
+            amount.0 <= available,
+            format!(
+                "The amount is greater than the withdrawable NEAR balance {}",
+                available
+            )
+        );
+        self.near_withdrawn += amount.0;
+        let receiver_id = env::predecessor_account_id();
+        MinterEvent::AdminWithdrawNear(vec![NearWithdrawData {
+            receiver_id: &receiver_id,
+            amount,
+        }])
+        .emit();
+
+        Promise::new(receiver_id).transfer(NearToken::from_yoctonear(amount.0))
+    }
+
+    /// Owner set the payees of the revenue split, the undistributed proceeds are split with
+    /// the payees at the time of the distribution
+    pub fn set_payees(&mut self, payees: Vec<Payee>) {
+        self.assert_owner();
+        let total_share: u32 = payees.iter().map(|payee| u32::from(payee.share_bps)).sum();
+        require!(
+            total_share == u32::from(TOTAL_SHARE_BPS),
+            format!(
+                "Payee shares should sum to {} basis points",
+                TOTAL_SHARE_BPS
+            )
+        );
+        for (index, payee) in payees.iter().enumerate() {
+            require!(payee.share_bps > 0, "Payee share should be positive");
+            require!(
+                !payees[..index]
+                    .iter()
+                    .any(|other| other.account_id == payee.account_id),
+                "Duplicated payee"
+            );
+        }
+        MinterEvent::PayeesSet(
+            payees
+                .iter()
+                .map(|payee| PayeeData {
+                    account_id: &payee.account_id,
+                    share_bps: payee.share_bps,
+                })
+                .collect(),
+        )
+        .emit();
+        self.payees = payees;
+    }
+
+    /// Admin split the undistributed proceeds of the token between the payees,
+    /// the rounding remainder stays undistributed
+    pub fn distribute(&mut self, token_account_id: AccountId) {
+        self.assert_role(Role::Treasurer);
+        self.assert_proceeds_unlocked();
+        require!(!self.payees.is_empty(), "No payees configured");
+        let amount = self
+            .proceeds
+            .get(&token_account_id)
+            .unwrap_or(U128::from(0));
+        require!(amount.0 > 0, "Nothing to distribute");
+
+        let mut distributed = 0;
+        let mut payouts = vec![];
+        for payee in &self.payees {
+            let share = amount.0.checked_mul(u128::from(payee.share_bps)).unwrap()
+                / u128::from(TOTAL_SHARE_BPS);
+            let key = (payee.account_id.clone(), token_account_id.clone());
+            let payout = self.payouts.get(&key).unwrap_or(Payout {
+                claimable: U128::from(0),
+                paid: U128::from(0),
+            });
+            self.payouts.insert(
+                &key,
+                &Payout {
+                    claimable: U128::from(payout.claimable.0 + share),
+                    paid: payout.paid,
+                },
+            );
+            distributed += share;
+            payouts.push((payee.account_id.clone(), share));
+        }
+        self.proceeds
+            .insert(&token_account_id, &U128::from(amount.0 - distributed));
+
+        MinterEvent::Distribute(
+            payouts
+                .iter()
+                .map(|(account_id, share)| PayoutData {
+                    account_id,
+                    token_account_id: &token_account_id,
+                    amount: U128::from(*share),
+                })
+                .collect(),
+        )
+        .emit();
+    }
+
+    /// Payee claim the proceeds distributed in the token
+    pub fn claim_payout(&mut self, token_account_id: AccountId) -> Promise {
+        self.assert_proceeds_unlocked();
+        let account_id = env::predecessor_account_id();
+        let key = (account_id.clone(), token_account_id.clone());
+        let payout = self.payouts.get(&key).expect("No payout found");
+        let amount = payout.claimable;
+        require!(amount.0 > 0, "Nothing to claim");
+        self.payouts.insert(
+            &key,
+            &Payout {
+                claimable: U128::from(0),
+                paid: U128::from(payout.paid.0 + amount.0),
+            },
+        );
+        self.internal_update_totals(&token_account_id, |totals| {
+            totals.withdrawn = U128::from(totals.withdrawn.0 + amount.0)
+        });
+        MinterEvent::PayoutClaim(vec![PayoutData {
+            account_id: &account_id,
+            token_account_id: &token_account_id,
+            amount,
+        }])
+        .emit();
+
+        Promise::new(token_account_id.clone())
+            .function_call(
+                "ft_transfer".to_string(),
+                json!({
+                "receiver_id": account_id,
+                "amount": amount
+                })
+                .to_string()
+                .as_bytes()
+                .to_vec(),
+                NearToken::from_yoctonear(1),
+                Gas::from_gas(DEFAULT_GAS),
+            )
+            .then(
+                Promise::new(env::current_account_id()).function_call(
+                    "on_payout_claimed".to_string(),
+                    json!({
+                        "account_id": account_id,
+                        "token_account_id": token_account_id,
+                        "amount": amount
+                    })
+                    .to_string()
+                    .as_bytes()
+                    .to_vec(),
+                    NearToken::from_yoctonear(0),
+                    Gas::from_gas(DEFAULT_GAS),
+                ),
+            )
+    }
+
+    /// Callback of the payout transfer, the payout is claimable again if the transfer failed
+    #[private]
+    pub fn on_payout_claimed(
+        &mut self,
+        account_id: AccountId,
+        token_account_id: AccountId,
+        amount: U128,
+    ) {
+        require!(env::promise_results_count() == 1);
+        if let PromiseResult::Failed = env::promise_result(0) {
+            log!(format!(
+                "Payout transfer of {} failed for {}",
+                token_account_id, account_id
+            ));
+            self.internal_update_totals(&token_account_id, |totals| {
+                totals.withdrawn = U128::from(totals.withdrawn.0 - amount.0)
+            });
+            let key = (account_id, token_account_id);
+            let payout = self.payouts.get(&key).unwrap();
+            self.payouts.insert(
+                &key,
+                &Payout {
+                    claimable: U128::from(payout.claimable.0 + amount.0),
+                    paid: U128::from(payout.paid.0 - amount.0),
+                },
+            );
+        }
+    }
+
+    /// Owner set the soft cap, the proceeds are locked until it is reached. It requires the
+    /// sale end and can't be set once proceeds were withdrawn so every refund stays covered
+    pub fn set_soft_cap(&mut self, soft_cap: Option<SoftCap>) {
+        self.assert_owner();
+        require!(!self.sale_cancelled, "The sale is cancelled");
+        require!(!self.internal_sale_ended(), "The sale already ended");
+        match &soft_cap {
+            Some(SoftCap::Packs(packs)) => require!(*packs > 0, "Soft cap should be positive"),
+            Some(SoftCap::Proceeds(amount)) => {
+                require!(amount.0 > 0, "Soft cap should be positive");
+                require!(
+                    self.near_minting_price.is_none(),
+                    "The NEAR payment is not counted in the proceeds soft cap"
+                );
             }
+            None => {}
         }
+        if soft_cap.is_some() {
+            require!(
+                self.public_sale_end.is_some(),
+                "Set the sale end before the soft cap"
+            );
+        }
+        self.assert_nothing_withdrawn();
+        MinterEvent::SoftCapSet(vec![SoftCapData {
+            soft_cap: soft_cap.as_ref(),
+        }])
+        .emit();
+        self.soft_cap = soft_cap;
     }
-}
 
-#[cfg(test)]
-#[cfg(all(test, not(target_arch = "wasm32")))]
-mod tests {
-    use super::*;
-    use near_sdk::test_utils::VMContextBuilder;
-    use near_sdk::{testing_env, AccountId, VMContext, ONE_YOCTO};
+    /// Owner enable the burning of the NFT packs returned with a refund, the NFT pack
+    /// contract should implement nft_burn
+    pub fn set_pack_burn(&mut self, pack_burn: bool) {
+        self.assert_owner();
+        MinterEvent::PackBurnSet(vec![PackBurnData { pack_burn }]).emit();
+        self.pack_burn = pack_burn;
+    }
 
-    fn get_context(is_view: bool) -> VMContext {
-        VMContextBuilder::new()
-            .signer_account_id(AccountId::new_unchecked("admin_near".to_string()))
-            .is_view(is_view)
-            .build()
+    /// Owner cancel the sale, minting stops and the buyers claim back the payment tokens
+    /// and the NEAR paid
+    pub fn cancel_sale(&mut self) {
+        self.assert_owner();
+        require!(!self.sale_cancelled, "The sale is already cancelled");
+        self.assert_nothing_withdrawn();
+        self.sale_cancelled = true;
+        MinterEvent::SaleCancel(vec![SaleCancelData {
+            packs_sold: self.packs_sold,
+            sale_proceeds: U128::from(self.sale_proceeds),
+        }])
+        .emit();
     }
 
-    fn default_minter_init() -> Minter {
-        self::Minter::new(
-            U128::from(100),
-            AccountId::new_unchecked("usdc_near".to_string()),
-            AccountId::new_unchecked("usdt_near".to_string()),
-            //AccountId::new_unchecked("dai_near".to_string()),
-            100,
-            200,
-            5000,
+    /// Claim back the amount paid in the token (None for NEAR) once the sale is cancelled or
+    /// ended below the soft cap, the NFT packs bought with it are returned if pack burn is enabled
+    pub fn claim_refund(
+        &mut self,
+        payment_token: Option<AccountId>,
+        token_ids: Option<Vec<String>>,
+    ) -> Promise {
+        require!(self.internal_refunds_open(), "Refunds are not open");
+        let account_id = env::predecessor_account_id();
+        let (purchase, auction_purchase) = match &payment_token {
+            Some(token) => {
+                let key = (account_id.clone(), token.clone());
+                let purchase = self.purchases.remove(&key).expect("No refund to claim");
+                self.internal_update_totals(token, |totals| {
+                    totals.refunded = U128::from(totals.refunded.0 + purchase.paid.0)
+                });
+                // The rebate is part of the refund
+                (purchase, self.auction_purchases.remove(&key))
+            }
+            None => (
+                self.near_purchases
+                    .remove(&account_id)
+                    .expect("No refund to claim"),
+                None,
+            ),
+        };
+        MinterEvent::RefundClaim(vec![RefundData {
+            account_id: &account_id,
+            payment_token: payment_token.as_ref(),
+            amount: purchase.paid,
+        }])
+        .emit();
+
+        if !self.pack_burn {
+            return self.internal_transfer_refund(
+                account_id,
+                payment_token,
+                purchase,
+                auction_purchase,
+            );
+        }
+        let token_ids = token_ids.expect("The NFT packs should be returned with the refund");
+        require!(
+            token_ids.len() == usize::from(purchase.packs),
+            format!("Requires {} NFT packs to be returned", purchase.packs)
+        );
+        promise_burn_packs(
+            self.nft_pack_contract.clone(),
+            &token_ids,
+            &account_id,
+            json!({
+                "account_id": account_id,
+                "payment_token": payment_token,
+                "purchase": purchase,
+                "auction_purchase": auction_purchase
+            }),
         )
     }
 
+    /// Callback of the NFT packs burn, the refund is transferred once the packs are burned
+    /// and the purchase is restored if the burn failed
+    #[private]
+    pub fn on_refund_packs_burned(
+        &mut self,
+        account_id: AccountId,
+        payment_token: Option<AccountId>,
+        purchase: Purchase,
+        auction_purchase: Option<Purchase>,
+    ) {
+        require!(env::promise_results_count() == 1);
+        match env::promise_result(0) {
+            PromiseResult::Successful(_) => {
+                self.internal_transfer_refund(
+                    account_id,
+                    payment_token,
+                    purchase,
+                    auction_purchase,
+                );
+            }
+            PromiseResult::Failed => {
+                log!(format!(
+                    "NFT packs burn failed for {}, refund cancelled",
+                    account_id
+                ));
+                self.internal_restore_refund(account_id, payment_token, purchase, auction_purchase);
+            }
+        }
+    }
+
+    /// Callback of the refund transfer, the purchase is restored if the transfer failed
+    #[private]
+    pub fn on_refund_claimed(
+        &mut self,
+        account_id: AccountId,
+        payment_token: Option<AccountId>,
+        purchase: Purchase,
+        auction_purchase: Option<Purchase>,
+    ) {
+        require!(env::promise_results_count() == 1);
+        if let PromiseResult::Failed = env::promise_result(0) {
+            log!(format!("Refund transfer failed for {}", account_id));
+            self.internal_restore_refund(account_id, payment_token, purchase, auction_purchase);
+        }
+    }
+
+    /// Queries
+    /// Query get whitelist by pagination from index + limit
+    pub fn get_whitelist(&self, from_index: u64, limit: u64) -> Vec<(AccountId, WhitelistState)> {
+        let keys = self.whitelist.keys_as_vector();
+        let values = self.whitelist.values_as_vector();
+        (from_index..std::cmp::min(from_index + limit, self.whitelist.len()))
+            .map(|index| (keys.get(index).unwrap(), values.get(index).unwrap()))
+            .collect()
+    }
+
+    /// Get the tiers by pagination from index + limit
+    pub fn get_tiers(&self, from_index: u64, limit: u64) -> Vec<(String, WhitelistTier)> {
+        let keys = self.tiers.keys_as_vector();
+        let values = self.tiers.values_as_vector();
+        (from_index..std::cmp::min(from_index + limit, self.tiers.len()))
+            .map(|index| (keys.get(index).unwrap(), values.get(index).unwrap()))
+            .collect()
+    }
+
+    /// Get the public key signing the vouchers
+    pub fn get_voucher_public_key(&self) -> Option<PublicKey> {
+        self.voucher_public_key.clone()
+    }
+
+    /// Check if the voucher nonce is already used
+    pub fn is_voucher_used(&self, nonce: u64) -> bool {
+        self.used_voucher_nonces.contains(&nonce)
+    }
+
+    /// Get the root of the Merkle whitelist
+    pub fn get_merkle_root(&self) -> Option<Base58CryptoHash> {
+        self.merkle_root
+    }
+
+    /// Get the tier of the account
+    pub fn get_tier_of(&self, account_id: AccountId) -> Option<String> {
+        self.tier_members.get(&account_id)
+    }
+
+    /// Get the owner and the proposed owner
+    pub fn get_owner(&self) -> (AccountId, Option<AccountId>) {
+        (self.admin.clone(), self.pending_admin.clone())
+    }
+
+    /// Get the roles granted to an account
+    pub fn get_roles(&self, account_id: AccountId) -> Vec<Role> {
+        self.roles.get(&account_id).unwrap_or_default()
+    }
+
+    /// Get the accepted payment tokens
+    pub fn get_payment_tokens(&self) -> Vec<(AccountId, PaymentToken)> {
+        self.payment_tokens.to_vec()
+    }
+
+    /// Get if the minting is paused for the scope
+    pub fn is_paused(&self, scope: PauseScope) -> bool {
+        match scope {
+            PauseScope::All => self.switch_off,
+            PauseScope::PublicSale => self.public_sale_paused,
+            PauseScope::PaymentToken(token_account_id) => {
+                self.payment_tokens
+                    .get(&token_account_id)
+                    .expect("Payment token not found")
+                    .paused
+            }
+        }
+    }
+
+    /// Get the storage deposit attached to every nft_mint and the bytes used by a NFT pack
+    pub fn get_mint_storage(&self) -> (U128, StorageUsage) {
+        (self.mint_storage_cost, self.mint_storage_usage)
+    }
+
+    /// Get the Dutch auction of the public sale
+    pub fn get_dutch_auction(&self) -> Option<DutchAuction> {
+        self.dutch_auction.clone()
+    }
+
+    /// Get the current and next public sale price, the Dutch auction price before
+    /// the public sale is the start price
+    pub fn get_public_sale_price(&self) -> PublicSalePrice {
+        match &self.dutch_auction {
+            None => PublicSalePrice {
+                current_price: self.minting_price,
+                next_price: None,
+                next_price_at: None,
+            },
+            Some(auction) => {
+                let elapsed = env::block_timestamp().saturating_sub(self.public_sale_start);
+                let current_price = auction.price_at(elapsed);
+                if current_price == auction.floor_price.0 {
+                    return PublicSalePrice {
+                        current_price: U128::from(current_price),
+                        next_price: None,
+                        next_price_at: None,
+                    };
+                }
+                let next_step = elapsed / auction.step_interval + 1;
+                let next_elapsed = next_step * auction.step_interval;
+                PublicSalePrice {
+                    current_price: U128::from(current_price),
+                    next_price: Some(U128::from(auction.price_at(next_elapsed))),
+                    next_price_at: Some(self.public_sale_start + next_elapsed),
+                }
+            }
+        }
+    }
+
+    /// Get the clearing price of the closed Dutch auction
+    pub fn get_clearing_price(&self) -> Option<U128> {
+        self.clearing_price
+    }
+
+    /// Get the rebate claimable by the account in the payment token
+    pub fn get_auction_rebate(&self, account_id: AccountId, payment_token: AccountId) -> U128 {
+        U128::from(self.internal_rebate(&account_id, &payment_token))
+    }
+
+    /// Get the soft cap and the progress of the sale
+    pub fn get_soft_cap(&self) -> SoftCapStatus {
+        SoftCapStatus {
+            soft_cap: self.soft_cap.clone(),
+            packs_sold: self.packs_sold,
+            sale_proceeds: U128::from(self.sale_proceeds),
+            reached: self.internal_soft_cap_reached(),
+            refunds_open: self.internal_refunds_open(),
+        }
+    }
+
+    /// Get the refund claimable by the account in the payment token (None for NEAR),
+    /// 0 while refunds are not open
+    pub fn get_refund(&self, account_id: AccountId, payment_token: Option<AccountId>) -> U128 {
+        if !self.internal_refunds_open() {
+            return U128::from(0);
+        }
+        let purchase = match payment_token {
+            Some(token) => self.purchases.get(&(account_id, token)),
+            None => self.near_purchases.get(&account_id),
+        };
+        purchase.map_or(U128::from(0), |purchase| purchase.paid)
+    }
+
+    /// Get the minting price in yoctoNEAR
+    pub fn get_near_minting_price(&self) -> Option<U128> {
+        self.near_minting_price
+    }
+
+    /// Get the NFT pack contract account and deployment status
+    pub fn get_pack_contract(&self) -> (AccountId, PackContractStatus) {
+        (self.nft_pack_contract.clone(), self.pack_contract_status)
+    }
+
+    /// Get minting info from account id
+    pub fn get_minting_of(&self, account: AccountId) -> u16 {
+        require!(self.minted.contains_key(&account), "No account found");
+
+        self.minted.get(&account).unwrap_or_default()
+    }
+
+    /// Get the NFT pack supply still available to mint
+    pub fn get_nft_pack_supply(&self) -> u16 {
+        self.nft_pack_supply
+    }
+
+    /// Get the reserved supply left and the NFT pack already minted from it
+    pub fn get_reserved_supply(&self) -> (u16, u16) {
+        (self.reserved_supply, self.reserved_minted)
+    }
+
+    /// Get the NFT pack reserved and waiting for the mint result
+    pub fn get_pending_mints(&self) -> u16 {
+        self.nft_pack_pending
+    }
+
+    /// Get the amounts of the payment token received, refunded and withdrawn
+    pub fn get_payment_totals(&self, token_account_id: AccountId) -> PaymentTotals {
+        self.payment_totals
+            .get(&token_account_id)
+            .unwrap_or_default()
+    }
+
+    /// Get the NEAR balance the admin can withdraw
+    pub fn get_withdrawable_near(&self) -> U128 {
+        U128::from(self.internal_withdrawable_near())
+    }
+
+    /// Get the payees of the revenue split
+    pub fn get_payees(&self) -> Vec<Payee> {
+        self.payees.clone()
+    }
+
+    /// Get the proceeds of the token not distributed yet
+    pub fn get_proceeds(&self, token_account_id: AccountId) -> U128 {
+        self.proceeds
+            .get(&token_account_id)
+            .unwrap_or(U128::from(0))
+    }
+
+    /// Get the proceeds of the token the payee can still claim and has been paid
+    pub fn get_payout(&self, account_id: AccountId, token_account_id: AccountId) -> Payout {
+        self.payouts
+            .get(&(account_id, token_account_id))
+            .unwrap_or(Payout {
+                claimable: U128::from(0),
+                paid: U128::from(0),
+            })
+    }
+
+    /// Get the current phase of the sale
+    pub fn get_sale_phase(&self) -> SalePhase {
+        self.internal_sale_phase()
+    }
+
+    /// Get the private sale start, the public sale start and the sale end
+    pub fn get_sale_schedule(&self) -> (Timestamp, Timestamp, Option<Timestamp>) {
+        (
+            self.private_sale_start,
+            self.public_sale_start,
+            self.public_sale_end,
+        )
+    }
+}
+
+/*
+   near_bindgen 5.1 generates a MinterExt wrapper for every method without forwarding their
+   allow attribute, the methods above the clippy argument limit are kept in this module so
+   the allow only covers them and their wrappers
+*/
+#[allow(clippy::too_many_arguments)]
+mod wide_methods {
+    use super::*;
+
+    #[near_bindgen]
+    impl Minter {
+        /// Instantiate the contract
+        #[init]
+        pub fn new(
+            minting_price: U128,
+            payment_tokens: Vec<(AccountId, u8)>,
+            private_sale_start: u64,
+            public_sale_start: u64,
+            nft_pack_supply: u16,
+            metadata: NFTContractMetadata,
+            pack_account_prefix: String,
+        ) -> Self {
+            log!(format!("creator: {}", env::signer_account_id()));
+            require!(
+                private_sale_start < public_sale_start,
+                "The private sale should start before the public sale"
+            );
+            // Verify the NFT pack contract metadata
+            metadata.assert_valid();
+            require!(
+                !metadata.symbol.is_empty() && metadata.symbol.len() <= MAX_SYMBOL_LEN,
+                format!("Symbol should be 1 to {} characters", MAX_SYMBOL_LEN)
+            );
+            require!(
+                !pack_account_prefix.contains('.'),
+                "The NFT pack account prefix should not contain a dot"
+            );
+            let subaccount_id: AccountId =
+                format!("{}.{}", pack_account_prefix, env::current_account_id())
+                    .parse()
+                    .expect("Not a valid NFT pack account id");
+
+            let mut accepted_tokens = UnorderedMap::new(b"p");
+            for (token_account_id, decimals) in payment_tokens {
+                PaymentToken::assert_decimals(decimals);
+                accepted_tokens.insert(
+                    &token_account_id,
+                    &PaymentToken {
+                        decimals,
+                        enabled: true,
+                        paused: false,
+                    },
+                );
+            }
+
+            let this = Self {
+                whitelist: UnorderedMap::new(b"s"),
+                tiers: UnorderedMap::new(b"t"),
+                tier_members: LookupMap::new(b"u"),
+                merkle_root: None,
+                voucher_public_key: None,
+                used_voucher_nonces: LookupSet::new(b"n"),
+                minting_price,
+                admin: env::predecessor_account_id(),
+                pending_admin: None,
+                roles: UnorderedMap::new(b"o"),
+                payment_tokens: accepted_tokens,
+                near_minting_price: None,
+                dutch_auction: None,
+                auction_purchases: LookupMap::new(b"a"),
+                auction_last_price: None,
+                clearing_price: None,
+                auction_totals: LookupMap::new(b"v"),
+                payees: vec![],
+                proceeds: LookupMap::new(b"c"),
+                payouts: LookupMap::new(b"y"),
+                payment_totals: LookupMap::new(b"k"),
+                purchases: LookupMap::new(b"b"),
+                near_purchases: LookupMap::new(b"e"),
+                soft_cap: None,
+                sale_cancelled: false,
+                packs_sold: 0,
+                sale_proceeds: 0,
+                near_withdrawn: 0,
+                pack_burn: false,
+                private_sale_start,
+                public_sale_start,
+                public_sale_end: None,
+                switch_off: false,
+                public_sale_paused: false,
+                minted: LookupMap::new(b"m"),
+                storage_deposits: LookupMap::new(b"d"),
+                total_storage_deposits: 0,
+                migrated_deposits: false,
+                pending_near_payments: 0,
+                mint_storage_cost: U128::from(MINT_STORAGE_COST),
+                mint_storage_usage: MINT_STORAGE_USAGE,
+                nft_pack_contract: subaccount_id,
+                nft_pack_supply,
+                nft_pack_pending: 0,
+                token_id_cursor: nft_pack_supply,
+                released_token_ids: Vector::new(b"r"),
+                reserved_supply: 0,
+                reserved_minted: 0,
+                pack_metadata: metadata,
+                pack_contract_status: PackContractStatus::Pending,
+            };
+            this.internal_deploy_pack_contract();
+            upgrade::write_state_version();
+            this.emit_phase_change();
+            this
+        }
+
+        /// Callback of the NFT pack minting, returns the amount of FT to refund to the sender,
+        /// the overpayment of a Dutch auction is always refunded.
+        /// Packs paid in NEAR (payment_token None) are refunded with a transfer instead
+        #[private]
+        pub fn mint_result(
+            &mut self,
+            token_ids: Vec<String>,
+            sender_id: AccountId,
+            storage_used: U128,
+            storage_refund: U128,
+            refund_amount: U128,
+            overpayment: U128,
+            auction_price: Option<U128>,
+            payment_token: Option<AccountId>,
+            voucher_nonce: Option<u64>,
+        ) -> U128 {
+            require!(env::promise_results_count() == 1);
+            let mint_amount = token_ids.len() as u16;
+            self.nft_pack_pending = self.nft_pack_pending.checked_sub(mint_amount).unwrap();
+            if payment_token.is_none() {
+                self.pending_near_payments =
+                    self.pending_near_payments.saturating_sub(refund_amount.0);
+            }
+            match env::promise_result(0) {
+                PromiseResult::Successful(_) => {
+                    MinterEvent::MintSucceeded(vec![PurchaseData {
+                        buyer_id: &sender_id,
+                        token_ids: &token_ids,
+                        payment_token: payment_token.as_ref(),
+                        amount: refund_amount,
+                    }])
+                    .emit();
+                    self.emit_supply_change();
+                    // Credit back the storage deposit refunded by the NFT pack contract
+                    self.internal_credit_storage(&sender_id, storage_refund.0);
+                    self.packs_sold = self.packs_sold.checked_add(mint_amount).unwrap();
+                    self.internal_record_purchase(
+                        &sender_id,
+                        payment_token.as_ref(),
+                        mint_amount,
+                        refund_amount,
+                    );
+                    match (auction_price, &payment_token) {
+                        (Some(price), Some(token)) => self.internal_record_auction_purchase(
+                            &sender_id,
+                            token,
+                            mint_amount,
+                            refund_amount,
+                            price,
+                        ),
+                        (None, Some(token)) => self.internal_add_proceeds(token, refund_amount.0),
+                        _ => {}
+                    }
+                    if let Some(token) = &payment_token {
+                        self.internal_update_totals(token, |totals| {
+                            totals.refunded = U128::from(totals.refunded.0 + overpayment.0)
+                        });
+                    }
+                    overpayment
+                }
+                PromiseResult::Failed => {
+                    /*
+                       Roll back the purchase, the returned amount is used by the FT contract
+                       to refund the sender in ft_resolve_transfer
+                    */
+                    MinterEvent::MintFailed(vec![PurchaseData {
+                        buyer_id: &sender_id,
+                        token_ids: &token_ids,
+                        payment_token: payment_token.as_ref(),
+                        amount: refund_amount,
+                    }])
+                    .emit();
+                    self.internal_release_token_ids(&token_ids);
+                    let amount_minted = self.minted.get(&sender_id).unwrap_or_default();
+                    self.minted
+                        .insert(&sender_id, &amount_minted.saturating_sub(mint_amount));
+                    self.internal_credit_storage(&sender_id, storage_used.0);
+                    // The voucher can be used again for a new purchase
+                    if let Some(nonce) = voucher_nonce {
+                        self.used_voucher_nonces.remove(&nonce);
+                    }
+
+                    match payment_token {
+                        Some(token) => {
+                            let refund = refund_amount.0 + overpayment.0;
+                            self.internal_update_totals(&token, |totals| {
+                                totals.refunded = U128::from(totals.refunded.0 + refund)
+                            });
+                            U128::from(refund)
+                        }
+                        None => {
+                            Promise::new(sender_id)
+                                .transfer(NearToken::from_yoctonear(refund_amount.0));
+                            U128::from(0)
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Minter {
+    fn assert_owner(&self) {
+        require!(
+            env::predecessor_account_id() == self.admin,
+            "Owner's method"
+        );
+    }
+
+    fn assert_proceeds_unlocked(&self) {
+        require!(!self.sale_cancelled, "The sale is cancelled");
+        require!(
+            self.internal_soft_cap_reached(),
+            "The proceeds are locked until the soft cap is reached"
+        );
+    }
+
+    fn assert_nothing_withdrawn(&self) {
+        require!(
+            self.near_withdrawn == 0
+                && self.payment_tokens.keys().all(|token_account_id| {
+                    self.payment_totals
+                        .get(&token_account_id)
+                        .unwrap_or_default()
+                        .withdrawn
+                        .0
+                        == 0
+                }),
+            "Proceeds were already withdrawn"
+        );
+    }
+
+    fn assert_role(&self, role: Role) {
+        let account_id = env::predecessor_account_id();
+        require!(
+            account_id == self.admin
+                || self
+                    .roles
+                    .get(&account_id)
+                    .is_some_and(|roles| roles.contains(&role)),
+            format!("Requires {:?} role", role)
+        );
+    }
+
+    fn internal_batch_out_of_gas() -> bool {
+        env::prepaid_gas()
+            .as_gas()
+            .saturating_sub(env::used_gas().as_gas())
+            < BATCH_GAS_RESERVE
+    }
+
+    /// Add the whitelist entries, upsert also update the accounts already whitelisted.
+    /// The minting start of an updated account can be kept even if already started
+    fn internal_whitelist_batch(
+        &mut self,
+        entries: Vec<WhitelistEntry>,
+        upsert: bool,
+    ) -> Vec<BatchResult> {
+        let mut added = vec![];
+        let mut updated = vec![];
+        let results = entries
+            .into_iter()
+            .map(|entry| {
+                let current = self.whitelist.get(&entry.account_id);
+                let status = if Self::internal_batch_out_of_gas() {
+                    BatchStatus::Skipped
+                } else if current.is_some() && !upsert {
+                    BatchStatus::AlreadyExist
+                } else if entry.minting_start < env::block_timestamp()
+                    && current.as_ref().map(|state| state.minting_start)
+                        != Some(entry.minting_start)
+                {
+                    BatchStatus::InvalidMintingStart
+                } else {
+                    self.whitelist.insert(
+                        &entry.account_id,
+                        &WhitelistState {
+                            minting_start: entry.minting_start,
+                            minting_price: entry.minting_price,
+                            minting_limit: entry.minting_limit,
+                        },
+                    );
+                    if current.is_some() {
+                        updated.push(entry.clone());
+                        BatchStatus::Updated
+                    } else {
+                        added.push(entry.clone());
+                        BatchStatus::Added
+                    }
+                };
+                BatchResult {
+                    account_id: entry.account_id,
+                    status,
+                }
+            })
+            .collect();
+
+        if !added.is_empty() {
+            MinterEvent::WhitelistAdd(added.iter().map(WhitelistEntry::event_data).collect())
+                .emit();
+        }
+        if !updated.is_empty() {
+            MinterEvent::WhitelistUpdate(updated.iter().map(WhitelistEntry::event_data).collect())
+                .emit();
+        }
+        results
+    }
+
+    /*
+        Allows our contract to deploy the NFT pack contract as admin more info for
+        dev help https://www.near-sdk.io/promises/deploy-contract
+    */
+    /// Create the NFT pack sub-account, deploy the contract and confirm it in a callback
+    fn internal_deploy_pack_contract(&self) -> Promise {
+        Promise::new(self.nft_pack_contract.clone())
+            .create_account()
+            .transfer(NearToken::from_yoctonear(MIN_DEPOSIT_CREATING_ACCOUNT))
+            .add_full_access_key(env::signer_account_pk())
+            .deploy_contract(CODE.to_vec())
+            .function_call(
+                "new".to_string(),
+                json!({
+                    "owner_id": env::current_account_id(),
+                    "metadata": self.pack_metadata
+                })
+                .to_string()
+                .as_bytes()
+                .to_vec(),
+                NearToken::from_yoctonear(0),
+                Gas::from_gas(DEFAULT_GAS),
+            )
+            .then(Promise::new(env::current_account_id()).function_call(
+                "on_pack_contract_deployed".to_string(),
+                json!({}).to_string().as_bytes().to_vec(),
+                NearToken::from_yoctonear(0),
+                Gas::from_gas(DEFAULT_GAS),
+            ))
+    }
+
+    fn internal_set_paused(&mut self, scope: &PauseScope, paused: bool) {
+        match scope {
+            PauseScope::All => self.switch_off = paused,
+            PauseScope::PublicSale => self.public_sale_paused = paused,
+            PauseScope::PaymentToken(token_account_id) => {
+                let mut payment_token = self
+                    .payment_tokens
+                    .get(token_account_id)
+                    .expect("Payment token not found");
+                payment_token.paused = paused;
+                self.payment_tokens.insert(token_account_id, &payment_token);
+            }
+        }
+    }
+
+    /// Reserve the supply and the token ids of the NFT pack before dispatching the mint
+    fn internal_reserve_token_ids(&mut self, mint_amount: u16) -> Vec<String> {
+        require!(
+            self.nft_pack_supply >= mint_amount,
+            format!(
+                "Supply limit reached. Left {} NFT pack",
+                self.nft_pack_supply
+            )
+        );
+        self.nft_pack_supply -= mint_amount;
+        let token_ids = self.internal_take_token_ids(mint_amount);
+        self.emit_supply_change();
+        token_ids
+    }
+
+    /// Token ids shared by the supply and the reserved supply, pending until the mint result
+    fn internal_take_token_ids(&mut self, mint_amount: u16) -> Vec<String> {
+        self.nft_pack_pending = self.nft_pack_pending.checked_add(mint_amount).unwrap();
+        (0..mint_amount)
+            .map(|_| {
+                self.released_token_ids.pop().unwrap_or_else(|| {
+                    self.token_id_cursor -= 1;
+                    self.token_id_cursor
+                })
+            })
+            .map(|token_id| token_id.to_string())
+            .collect()
+    }
+
+    fn internal_return_token_ids(&mut self, token_ids: &[String]) {
+        for token_id in token_ids {
+            self.released_token_ids
+                .push(&token_id.parse().expect("Invalid token id"));
+        }
+    }
+
+    /// Give back to the supply the token ids of a failed mint
+    fn internal_release_token_ids(&mut self, token_ids: &[String]) {
+        self.internal_return_token_ids(token_ids);
+        self.nft_pack_supply = self
+            .nft_pack_supply
+            .checked_add(token_ids.len() as u16)
+            .unwrap();
+        self.emit_supply_change();
+    }
+
+    fn internal_withdrawable_near(&self) -> u128 {
+        let storage_cost =
+            u128::from(env::storage_usage()) * env::storage_byte_cost().as_yoctonear();
+        env::account_balance()
+            .as_yoctonear()
+            .saturating_sub(storage_cost)
+            .saturating_sub(self.total_storage_deposits)
+            .saturating_sub(self.pending_near_payments)
+    }
+
+    fn internal_credit_storage(&mut self, account_id: &AccountId, amount: u128) {
+        if amount == 0 {
+            return;
+        }
+        self.total_storage_deposits += amount;
+        let balance = self
+            .storage_deposits
+            .get(account_id)
+            .unwrap_or(U128::from(0));
+        self.storage_deposits.insert(
+            account_id,
+            &U128::from(balance.0.checked_add(amount).unwrap()),
+        );
+    }
+
+    /// Save the packs bought at the auction price, the auction is closed once sold out
+    fn internal_record_auction_purchase(
+        &mut self,
+        buyer_id: &AccountId,
+        payment_token: &AccountId,
+        packs: u16,
+        paid: U128,
+        price: U128,
+    ) {
+        let key = (buyer_id.clone(), payment_token.clone());
+        let purchase = self.auction_purchases.get(&key).unwrap_or_default();
+        self.auction_purchases.insert(
+            &key,
+            &Purchase {
+                packs: purchase.packs.checked_add(packs).unwrap(),
+                paid: U128::from(purchase.paid.0.checked_add(paid.0).unwrap()),
+            },
+        );
+        let totals = self.auction_totals.get(payment_token).unwrap_or_default();
+        self.auction_totals.insert(
+            payment_token,
+            &Purchase {
+                packs: totals.packs.checked_add(packs).unwrap(),
+                paid: U128::from(totals.paid.0.checked_add(paid.0).unwrap()),
+            },
+        );
+        // Purchases resolved after the auction closed are only kept at the clearing price,
+        // never above the amount paid
+        if let Some(clearing_price) = self.clearing_price {
+            let token = self.payment_tokens.get(payment_token).unwrap();
+            self.internal_add_proceeds(
+                payment_token,
+                token
+                    .to_token_amount(clearing_price.0 * u128::from(packs))
+                    .min(paid.0),
+            );
+        }
+        self.auction_last_price = Some(
+            self.auction_last_price
+                .map_or(price, |last| U128::from(last.0.min(price.0))),
+        );
+        if self.clearing_price.is_none() && self.nft_pack_supply == 0 && self.nft_pack_pending == 0
+        {
+            self.internal_close_auction();
+        }
+    }
+
+    fn internal_close_auction(&mut self) {
+        let clearing_price = self
+            .auction_last_price
+            .unwrap_or_else(|| U128::from(self.get_public_sale_price().current_price.0));
+        self.clearing_price = Some(clearing_price);
+        MinterEvent::AuctionClose(vec![AuctionCloseData { clearing_price }]).emit();
+
+        // The auction proceeds are kept at the clearing price, the rest is owed as rebates
+        for (token_account_id, token) in self.payment_tokens.to_vec() {
+            if let Some(totals) = self.auction_totals.get(&token_account_id) {
+                self.internal_add_proceeds(
+                    &token_account_id,
+                    token.to_token_amount(clearing_price.0 * u128::from(totals.packs)),
+                );
+            }
+        }
+    }
+
+    /// Save the packs bought and the amount paid, refunded if the soft cap is missed.
+    /// The NEAR paid (payment_token None) is not counted in the sale proceeds
+    fn internal_record_purchase(
+        &mut self,
+        buyer_id: &AccountId,
+        payment_token: Option<&AccountId>,
+        packs: u16,
+        paid: U128,
+    ) {
+        let update = |purchase: &mut Purchase| {
+            purchase.packs = purchase.packs.checked_add(packs).unwrap();
+            purchase.paid = U128::from(purchase.paid.0.checked_add(paid.0).unwrap());
+        };
+        let payment_token = match payment_token {
+            Some(token) => token,
+            None => {
+                let mut purchase = self.near_purchases.get(buyer_id).unwrap_or_default();
+                update(&mut purchase);
+                self.near_purchases.insert(buyer_id, &purchase);
+                return;
+            }
+        };
+        let key = (buyer_id.clone(), payment_token.clone());
+        self.internal_update_purchase(&key, update);
+        let token = self.payment_tokens.get(payment_token).unwrap();
+        self.sale_proceeds = self
+            .sale_proceeds
+            .checked_add(token.to_price(paid.0))
+            .unwrap();
+    }
+
+    fn internal_update_purchase(
+        &mut self,
+        key: &(AccountId, AccountId),
+        update: impl FnOnce(&mut Purchase),
+    ) {
+        let mut purchase = self.purchases.get(key).unwrap_or_default();
+        update(&mut purchase);
+        self.purchases.insert(key, &purchase);
+    }
+
+    fn internal_soft_cap_reached(&self) -> bool {
+        match &self.soft_cap {
+            None => true,
+            Some(SoftCap::Packs(packs)) => self.packs_sold >= *packs,
+            Some(SoftCap::Proceeds(amount)) => self.sale_proceeds >= amount.0,
+        }
+    }
+
+    /// The public sale end is passed or the Dutch auction is closed
+    fn internal_sale_ended(&self) -> bool {
+        self.public_sale_end
+            .is_some_and(|end| env::block_timestamp() >= end)
+            || self.clearing_price.is_some()
+    }
+
+    fn internal_refunds_open(&self) -> bool {
+        self.sale_cancelled || (self.internal_sale_ended() && !self.internal_soft_cap_reached())
+    }
+
+    fn internal_transfer_refund(
+        &mut self,
+        account_id: AccountId,
+        payment_token: Option<AccountId>,
+        purchase: Purchase,
+        auction_purchase: Option<Purchase>,
+    ) -> Promise {
+        let transfer = match &payment_token {
+            Some(token) => Promise::new(token.clone()).function_call(
+                "ft_transfer".to_string(),
+                json!({
+                "receiver_id": account_id,
+                "amount": purchase.paid
+                })
+                .to_string()
+                .as_bytes()
+                .to_vec(),
+                NearToken::from_yoctonear(1),
+                Gas::from_gas(DEFAULT_GAS),
+            ),
+            None => Promise::new(account_id.clone())
+                .transfer(NearToken::from_yoctonear(purchase.paid.0)),
+        };
+        transfer.then(
+            Promise::new(env::current_account_id()).function_call(
+                "on_refund_claimed".to_string(),
+                json!({
+                    "account_id": account_id,
+                    "payment_token": payment_token,
+                    "purchase": purchase,
+                    "auction_purchase": auction_purchase
+                })
+                .to_string()
+                .as_bytes()
+                .to_vec(),
+                NearToken::from_yoctonear(0),
+                Gas::from_gas(DEFAULT_GAS),
+            ),
+        )
+    }
+
+    fn internal_restore_refund(
+        &mut self,
+        account_id: AccountId,
+        payment_token: Option<AccountId>,
+        purchase: Purchase,
+        auction_purchase: Option<Purchase>,
+    ) {
+        let payment_token = match payment_token {
+            Some(token) => token,
+            None => {
+                self.near_purchases.insert(&account_id, &purchase);
+                return;
+            }
+        };
+        let key = (account_id, payment_token);
+        self.internal_update_totals(&key.1, |totals| {
+            totals.refunded = U128::from(totals.refunded.0 - purchase.paid.0)
+        });
+        self.purchases.insert(&key, &purchase);
+        if let Some(auction_purchase) = auction_purchase {
+            self.auction_purchases.insert(&key, &auction_purchase);
+        }
+    }
+
+    fn internal_update_totals(
+        &mut self,
+        token_account_id: &AccountId,
+        update: impl FnOnce(&mut PaymentTotals),
+    ) {
+        let mut totals = self
+            .payment_totals
+            .get(token_account_id)
+            .unwrap_or_default();
+        update(&mut totals);
+        self.payment_totals.insert(token_account_id, &totals);
+    }
+
+    fn internal_add_proceeds(&mut self, token_account_id: &AccountId, amount: u128) {
+        let proceeds = self.proceeds.get(token_account_id).unwrap_or(U128::from(0));
+        self.proceeds.insert(
+            token_account_id,
+            &U128::from(proceeds.0.checked_add(amount).unwrap()),
+        );
+    }
+
+    /// Amount paid above the clearing price, 0 while the auction is not closed
+    fn internal_rebate(&self, account_id: &AccountId, payment_token: &AccountId) -> u128 {
+        let clearing_price = match self.clearing_price {
+            Some(clearing_price) => clearing_price,
+            None => return 0,
+        };
+        match self
+            .auction_purchases
+            .get(&(account_id.clone(), payment_token.clone()))
+        {
+            Some(purchase) => {
+                let token = self
+                    .payment_tokens
+                    .get(payment_token)
+                    .expect("Unknown payment token");
+                purchase.paid.0.saturating_sub(
+                    token.to_token_amount(clearing_price.0 * u128::from(purchase.packs)),
+                )
+            }
+            None => 0,
+        }
+    }
+
+    fn emit_phase_change(&self) {
+        MinterEvent::PhaseChange(vec![PhaseData {
+            private_sale_start: self.private_sale_start,
+            public_sale_start: self.public_sale_start,
+        }])
+        .emit();
+    }
+
+    /// Phase of the sale at the block timestamp, a closed Dutch auction ends the sale
+    fn internal_sale_phase(&self) -> SalePhase {
+        let time = env::block_timestamp();
+        if self.sale_cancelled {
+            SalePhase::Cancelled
+        } else if self.switch_off {
+            SalePhase::Paused
+        } else if time < self.private_sale_start {
+            SalePhase::NotStarted
+        } else if self.internal_sale_ended() {
+            SalePhase::Ended
+        } else if self.nft_pack_supply == 0 {
+            SalePhase::SoldOut
+        } else if time < self.public_sale_start {
+            SalePhase::Private
+        } else if self.public_sale_paused {
+            SalePhase::Paused
+        } else {
+            SalePhase::Public
+        }
+    }
+
+    fn emit_supply_change(&self) {
+        MinterEvent::SupplyChange(vec![SupplyData {
+            supply: self.nft_pack_supply,
+            pending: self.nft_pack_pending,
+        }])
+        .emit();
+    }
+
+    /// Verify the buyer is allowed to mint in the current sale phase and save the amount minted,
+    /// returns the whitelist state of the buyer during the private sale and the nonce of the
+    /// voucher used
+    fn internal_record_mint(
+        &mut self,
+        buyer_id: &AccountId,
+        mint_amount: u16,
+        merkle_proof: Option<MerkleProof>,
+        voucher: Option<SignedVoucher>,
+    ) -> (Option<WhitelistState>, Option<u64>) {
+        require!(
+            self.pack_contract_status == PackContractStatus::Deployed,
+            "NFT pack contract is not deployed"
+        );
+        require!(!self.switch_off, "Minting is paused");
+        require!(mint_amount > 0);
+        require!(
+            self.nft_pack_supply >= mint_amount,
+            format!(
+                "Supply limit reached. Left {} NFT pack",
+                self.nft_pack_supply
+            )
+        );
+        require!(
+            self.storage_deposits.contains_key(buyer_id),
+            "Action required deposit Near for storage"
+        );
+        let amount_minted = self.minted.get(buyer_id).unwrap_or_default();
+
+        let time = env::block_timestamp();
+        // Minting paused and sold out are verified above
+        let phase = self.internal_sale_phase();
+        require!(phase != SalePhase::Cancelled, "The sale is cancelled");
+        require!(phase != SalePhase::NotStarted, "Sale have not started yet");
+        require!(
+            self.clearing_price.is_none(),
+            "The Dutch auction is already closed"
+        );
+        require!(phase != SalePhase::Ended, "Sale has ended");
+
+        if phase != SalePhase::Private {
+            require!(phase != SalePhase::Paused, "Public sale is paused");
+            // Save the buyer to minted storage and increment the amount already minted
+            self.minted
+                .insert(buyer_id, &amount_minted.checked_add(mint_amount).unwrap());
+            return (None, None);
+        }
+
+        // The voucher allocation is independent of the amount already minted
+        if let Some(voucher) = voucher {
+            let voucher = self.internal_use_voucher(buyer_id, mint_amount, voucher);
+            self.minted
+                .insert(buyer_id, &amount_minted.checked_add(mint_amount).unwrap());
+            return (
+                Some(WhitelistState {
+                    minting_start: self.private_sale_start,
+                    minting_price: voucher.price,
+                    minting_limit: voucher.max_amount,
+                }),
+                Some(voucher.nonce),
+            );
+        }
+
+        // Verify the buyer is in the Merkle whitelist, a tier or the whitelist, the proven
+        // terms take precedence over the tier terms
+        let tier = self
+            .tier_members
+            .get(buyer_id)
+            .and_then(|tier| self.tiers.get(&tier));
+        let (whitelist_user, minting_end) = match (merkle_proof, tier) {
+            (Some(merkle_proof), _) => {
+                let merkle_root = self.merkle_root.expect("Merkle whitelist is not enabled");
+                let leaf = merkle_leaf(
+                    buyer_id,
+                    merkle_proof.minting_limit,
+                    merkle_proof.minting_price,
+                );
+                require!(
+                    verify_merkle_proof(leaf, &merkle_proof.proof, merkle_root),
+                    "Invalid Merkle proof"
+                );
+                (
+                    WhitelistState {
+                        minting_start: self.private_sale_start,
+                        minting_price: merkle_proof.minting_price,
+                        minting_limit: merkle_proof.minting_limit,
+                    },
+                    None,
+                )
+            }
+            (None, Some(tier)) => (
+                WhitelistState {
+                    minting_start: tier.minting_start,
+                    minting_price: tier.minting_price,
+                    minting_limit: tier.minting_limit,
+                },
+                Some(tier.minting_end),
+            ),
+            (None, None) => (
+                self.whitelist
+                    .get(buyer_id)
+                    .expect("The address is not in the whitelist"),
+                None,
+            ),
+        };
+        // Verify the private sale is open for the buyer
+        require!(
+            time >= whitelist_user.minting_start,
+            format!(
+                "Private sale not started yet for this account, starts at {}",
+                whitelist_user.minting_start
+            )
+        );
+        if let Some(minting_end) = minting_end {
+            require!(
+                time < minting_end,
+                format!(
+                    "Private sale ended for this account, ended at {}",
+                    minting_end
+                )
+            );
+        }
+        // Verify the buyer have not reached the minting limit
+        if amount_minted > 0 {
+            require!(
+                u16::from(whitelist_user.minting_limit)
+                    >= amount_minted.checked_add(mint_amount).unwrap(),
+                "Out of mint"
+            );
+        } else {
+            require!(
+                u16::from(whitelist_user.minting_limit) >= mint_amount,
+                format!(
+                    "Whitelisted account only allowed to mint {} NFTs pack",
+                    whitelist_user.minting_limit
+                )
+            );
+        }
+        // Save the buyer to minted storage and increment the amount already minted
+        self.minted
+            .insert(buyer_id, &amount_minted.checked_add(mint_amount).unwrap());
+        (Some(whitelist_user), None)
+    }
+
+    /// Verify the voucher is signed by the voucher key for the buyer and mark the nonce used
+    fn internal_use_voucher(
+        &mut self,
+        buyer_id: &AccountId,
+        mint_amount: u16,
+        signed_voucher: SignedVoucher,
+    ) -> Voucher {
+        let public_key = self
+            .voucher_public_key
+            .as_ref()
+            .expect("Vouchers are not enabled");
+        let voucher = signed_voucher.voucher;
+        require!(
+            &voucher.account_id == buyer_id,
+            "Voucher issued for another account"
+        );
+        require!(env::block_timestamp() < voucher.expiry, "Voucher expired");
+        require!(
+            mint_amount <= u16::from(voucher.max_amount),
+            format!(
+                "Voucher only allowed to mint {} NFTs pack",
+                voucher.max_amount
+            )
+        );
+        require!(
+            !self.used_voucher_nonces.contains(&voucher.nonce),
+            "Voucher already used"
+        );
+        // The minter account is signed with the voucher so it can't be replayed on another launch
+        let message = borsh::to_vec(&(env::current_account_id(), &voucher)).unwrap();
+        let signature: [u8; 64] = signed_voucher
+            .signature
+            .0
+            .as_slice()
+            .try_into()
+            .expect("Invalid voucher signature");
+        let public_key: [u8; 32] = public_key.as_bytes()[1..].try_into().unwrap();
+        require!(
+            env::ed25519_verify(&signature, &message, &public_key),
+            "Invalid voucher signature"
+        );
+        self.used_voucher_nonces.insert(&voucher.nonce);
+        voucher
+    }
+
+    /// Mint the NFT pack to the sender and debit the storage used from his deposit,
+    /// payment_token is None when the pack is paid in NEAR.
+    /// The overpayment is the FT amount sent above the price and refunded by mint_result
+    #[allow(clippy::too_many_arguments)]
+    fn internal_mint_pack(
+        &mut self,
+        sender_id: AccountId,
+        mint_amount: u16,
+        amount: U128,
+        overpayment: U128,
+        auction_price: Option<U128>,
+        payment_token: Option<AccountId>,
+        voucher_nonce: Option<u64>,
+    ) -> Promise {
+        let storage_deposit = self
+            .storage_deposits
+            .get(&sender_id)
+            .unwrap_or(U128::from(0));
+        // Mint the NFT pack and send it to the sender
+        let token_ids = self.internal_reserve_token_ids(mint_amount);
+        MinterEvent::PurchaseStarted(vec![PurchaseData {
+            buyer_id: &sender_id,
+            token_ids: &token_ids,
+            payment_token: payment_token.as_ref(),
+            amount,
+        }])
+        .emit();
+        let storage_used = u128::from(mint_amount) * self.mint_storage_cost.0;
+        require!(
+            storage_deposit.0 >= storage_used,
+            format!(
+                "Minimum required for storage deposit of {} Yocto Near",
+                storage_used
+            )
+        );
+        /*
+           Part of the storage deposit refunded by the NFT pack contract to the minter. The
+           usage is an upper bound, the actual refund depends on the length of the sender id
+           and the difference is left in the withdrawable NEAR balance
+        */
+        let storage_refund = storage_used
+            - u128::from(mint_amount)
+                * u128::from(self.mint_storage_usage)
+                * env::storage_byte_cost().as_yoctonear();
+        let promise = promise_mint_pack(
+            self.nft_pack_contract.clone(),
+            &token_ids,
+            &sender_id,
+            pack_token_metadata(),
+            self.mint_storage_cost.0,
+            "mint_result",
+            json!({
+                "token_ids": token_ids,
+                "sender_id": sender_id,
+                "storage_used": U128::from(storage_used),
+                "storage_refund": U128::from(storage_refund),
+                "refund_amount": amount,
+                "overpayment": overpayment,
+                "auction_price": auction_price,
+                "payment_token": payment_token,
+                "voucher_nonce": voucher_nonce
+            }),
+        );
+
+        self.storage_deposits
+            .insert(&sender_id, &U128::from(storage_deposit.0 - storage_used));
+        self.total_storage_deposits = self.total_storage_deposits.saturating_sub(storage_used);
+
+        promise
+    }
+}
+
+#[near_bindgen]
+impl FungibleTokenReceiver for Minter {
+    fn ft_on_transfer(
+        &mut self,
+        sender_id: AccountId,
+        amount: U128,
+        msg: String,
+    ) -> PromiseOrValue<U128> {
+        let token_account_id = env::predecessor_account_id();
+        let payment_token = self
+            .payment_tokens
+            .get(&token_account_id)
+            .expect("Only allowed NF contracts can call this message");
+        require!(payment_token.enabled, "Payment token disabled");
+        require!(!payment_token.paused, "Payment token is paused");
+
+        if msg.is_empty() {
+            log!("Missing msg in ft_transfer_call");
+            PromiseOrValue::Value(amount)
+        } else {
+            let message = serde_json::from_str::<TokenReceiverMessage>(&msg)
+                .expect("Illegal msg in ft_transfer_call");
+            match message {
+                TokenReceiverMessage::Mint {
+                    mint_amount,
+                    merkle_proof,
+                    voucher,
+                } => {
+                    if env::block_timestamp() < self.private_sale_start {
+                        log!("Sale have not started yet");
+                        return PromiseOrValue::Value(amount);
+                    }
+                    let (whitelist_user, voucher_nonce) =
+                        self.internal_record_mint(&sender_id, mint_amount, merkle_proof, voucher);
+
+                    // Verify the amount sent match with minting cost
+                    let mut overpayment = 0;
+                    let mut auction_price = None;
+                    match whitelist_user {
+                        Some(whitelist_user) => {
+                            let cost = payment_token.to_token_amount(
+                                whitelist_user.minting_price.0 * u128::from(mint_amount),
+                            );
+                            require!(
+                                amount.0 == cost,
+                                format!(
+                                    "Wrong amount sent, whitelist minting price {} {}",
+                                    cost, token_account_id
+                                )
+                            );
+                        }
+                        None => match &self.dutch_auction {
+                            None => {
+                                let cost = payment_token.to_token_amount(
+                                    self.minting_price.0 * u128::from(mint_amount),
+                                );
+                                require!(
+                                    amount.0 == cost,
+                                    format!(
+                                        "Wrong amount sent, minting price {} {}",
+                                        cost, token_account_id
+                                    )
+                                );
+                            }
+                            Some(auction) => {
+                                // The price may decrease before the transfer is received
+                                let elapsed = env::block_timestamp() - self.public_sale_start;
+                                let price = auction.price_at(elapsed);
+                                let cost =
+                                    payment_token.to_token_amount(price * u128::from(mint_amount));
+                                require!(
+                                    amount.0 >= cost,
+                                    format!(
+                                        "Not enough amount sent, current auction price {} {}",
+                                        cost, token_account_id
+                                    )
+                                );
+                                overpayment = amount.0 - cost;
+                                if auction.rebate {
+                                    auction_price = Some(U128::from(price));
+                                }
+                            }
+                        },
+                    }
+
+                    // The amounts returned without minting are not tracked as received
+                    self.internal_update_totals(&token_account_id, |totals| {
+                        totals.received = U128::from(totals.received.0 + amount.0)
+                    });
+                    PromiseOrValue::Promise(self.internal_mint_pack(
+                        sender_id,
+                        mint_amount,
+                        U128::from(amount.0 - overpayment),
+                        U128::from(overpayment),
+                        auction_price,
+                        Some(token_account_id),
+                        voucher_nonce,
+                    ))
+                }
+            }
+        }
+    }
+}
+
+/*
+   Near deposit storage, used as fee for minting NFT. The storage deposit is spent on every
+   mint so the whole balance is available
+*/
+#[near_bindgen]
+impl StorageManagement for Minter {
+    #[payable]
+    fn storage_deposit(
+        &mut self,
+        account_id: Option<AccountId>,
+        registration_only: Option<bool>,
+    ) -> StorageBalance {
+        let account_id = account_id.unwrap_or_else(env::predecessor_account_id);
+        let mut deposit = env::attached_deposit().as_yoctonear();
+        let balance = self.storage_deposits.get(&account_id);
+
+        if registration_only.unwrap_or(false) {
+            let refund = match balance {
+                // Already registered, refund the full deposit
+                Some(_) => deposit,
+                None => {
+                    require!(
+                        deposit >= self.mint_storage_cost.0,
+                        format!("Requires minimum deposit of {}", self.mint_storage_cost.0)
+                    );
+                    deposit - self.mint_storage_cost.0
+                }
+            };
+            if refund > 0 {
+                Promise::new(env::predecessor_account_id())
+                    .transfer(NearToken::from_yoctonear(refund));
+            }
+            deposit -= refund;
+        } else if balance.is_none() {
+            require!(
+                deposit >= self.mint_storage_cost.0,
+                format!("Requires minimum deposit of {}", self.mint_storage_cost.0)
+            );
+        }
+
+        let new_balance = U128::from(
+            balance
+                .unwrap_or(U128::from(0))
+                .0
+                .checked_add(deposit)
+                .unwrap(),
+        );
+        self.storage_deposits.insert(&account_id, &new_balance);
+        self.total_storage_deposits += deposit;
+        if deposit > 0 {
+            MinterEvent::StorageDeposit(vec![StorageData {
+                account_id: &account_id,
+                amount: U128::from(deposit),
+                balance: new_balance,
+            }])
+            .emit();
+        }
+
+        StorageBalance {
+            total: NearToken::from_yoctonear(new_balance.0),
+            available: NearToken::from_yoctonear(new_balance.0),
+        }
+    }
+
+    #[payable]
+    fn storage_withdraw(&mut self, amount: Option<NearToken>) -> StorageBalance {
+        assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+        let balance = self
+            .storage_deposits
+            .get(&account_id)
+            .expect("No account found");
+        let amount = amount.map_or(balance, |amount| U128::from(amount.as_yoctonear()));
+        require!(
+            amount.0 <= balance.0,
+            format!(
+                "The amount is greater than the available balance {}",
+                balance.0
+            )
+        );
+        let new_balance = U128::from(balance.0 - amount.0);
+        self.storage_deposits.insert(&account_id, &new_balance);
+        self.total_storage_deposits = self.total_storage_deposits.saturating_sub(amount.0);
+        if amount.0 > 0 {
+            MinterEvent::StorageWithdraw(vec![StorageData {
+                account_id: &account_id,
+                amount,
+                balance: new_balance,
+            }])
+            .emit();
+            Promise::new(account_id).transfer(NearToken::from_yoctonear(amount.0));
+        }
+
+        StorageBalance {
+            total: NearToken::from_yoctonear(new_balance.0),
+            available: NearToken::from_yoctonear(new_balance.0),
+        }
+    }
+
+    /// The minter holds no balance for the account other than the storage deposit,
+    /// force has no effect
+    #[payable]
+    fn storage_unregister(&mut self, force: Option<bool>) -> bool {
+        assert_one_yocto();
+        let _ = force;
+        let account_id = env::predecessor_account_id();
+        match self.storage_deposits.remove(&account_id) {
+            Some(balance) => {
+                self.total_storage_deposits = self.total_storage_deposits.saturating_sub(balance.0);
+                MinterEvent::StorageWithdraw(vec![StorageData {
+                    account_id: &account_id,
+                    amount: balance,
+                    balance: U128::from(0),
+                }])
+                .emit();
+                if balance.0 > 0 {
+                    Promise::new(account_id).transfer(NearToken::from_yoctonear(balance.0));
+                }
+                true
+            }
+            None => {
+                log!(format!("The account {} is not registered", account_id));
+                false
+            }
+        }
+    }
+
+    fn storage_balance_bounds(&self) -> StorageBalanceBounds {
+        // Minimum to mint a single NFT pack
+        StorageBalanceBounds {
+            min: NearToken::from_yoctonear(self.mint_storage_cost.0),
+            max: None,
+        }
+    }
+
+    fn storage_balance_of(&self, account_id: AccountId) -> Option<StorageBalance> {
+        self.storage_deposits
+            .get(&account_id)
+            .map(|balance| StorageBalance {
+                total: NearToken::from_yoctonear(balance.0),
+                available: NearToken::from_yoctonear(balance.0),
+            })
+    }
+}
+
+#[cfg(test)]
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+    use near_sdk::test_utils::{get_logs, VMContextBuilder};
+    use near_sdk::{
+        test_vm_config, testing_env, AccountId, CryptoHash, RuntimeFeesConfig, VMContext,
+    };
+    use std::convert::{TryFrom, TryInto};
+
+    const ONE_NEAR: u128 = 1_000_000_000_000_000_000_000_000;
+    const ONE_YOCTO: u128 = 1;
+
+    fn get_context(is_view: bool) -> VMContext {
+        VMContextBuilder::new()
+            .signer_account_id("admin_near".parse::<AccountId>().unwrap())
+            .predecessor_account_id("admin_near".parse::<AccountId>().unwrap())
+            .is_view(is_view)
+            .build()
+    }
+
+    fn default_minter_init() -> Minter {
+        let mut contract = self::Minter::new(
+            U128::from(100),
+            vec![
+                ("usdc_near".parse::<AccountId>().unwrap(), 6),
+                ("usdt_near".parse::<AccountId>().unwrap(), 6),
+            ],
+            100,
+            200,
+            5000,
+            default_pack_metadata(),
+            "nft_pack".to_string(),
+        );
+        // Simulate the NFT pack contract deployment callback
+        contract.pack_contract_status = PackContractStatus::Deployed;
+        contract
+    }
+
+    fn default_pack_metadata() -> NFTContractMetadata {
+        NFTContractMetadata {
+            spec: "nft-1.0.0".to_string(),
+            name: "LoTerra pack".to_string(),
+            symbol: "PACK".to_string(),
+            icon: None,
+            base_uri: None,
+            reference: None,
+            reference_hash: None,
+        }
+    }
+
+    #[test]
+    fn try_whitelist() {
+        let mut context = get_context(false);
+        context.signer_account_id = "admin_near".parse::<AccountId>().unwrap();
+        context.predecessor_account_id = "admin_near".parse::<AccountId>().unwrap();
+        testing_env!(context);
+
+        let mut contract = default_minter_init();
+        contract.add_whitelist(
+            "alice_near".parse::<AccountId>().unwrap(),
+            env::block_timestamp().checked_add(100).unwrap(),
+            U128::from(1000),
+            5,
+        );
+
+        assert_eq!(
+            contract.get_whitelist(0, 10),
+            vec![(
+                "alice_near".parse::<AccountId>().unwrap(),
+                WhitelistState {
+                    minting_start: 100,
+                    minting_price: U128::from(1000),
+                    minting_limit: 5
+                }
+            )]
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn try_whitelist_not_authorized() {
+        let mut context = get_context(false);
+        let mut contract = default_minter_init();
+        context.signer_account_id = "alice_near".parse::<AccountId>().unwrap();
+        context.predecessor_account_id = "alice_near".parse::<AccountId>().unwrap();
+        testing_env!(context);
+
+        contract.add_whitelist(
+            "alice_near".parse::<AccountId>().unwrap(),
+            env::block_timestamp().checked_add(100).unwrap(),
+            U128::from(10),
+            5,
+        )
+    }
+
+    fn whitelist_entry(account_id: &str, minting_start: Timestamp, limit: u8) -> WhitelistEntry {
+        WhitelistEntry {
+            account_id: account_id.parse::<AccountId>().unwrap(),
+            minting_start,
+            minting_price: U128::from(1000),
+            minting_limit: limit,
+        }
+    }
+
+    fn batch_result(account_id: &str, status: BatchStatus) -> BatchResult {
+        BatchResult {
+            account_id: account_id.parse::<AccountId>().unwrap(),
+            status,
+        }
+    }
+
+    #[test]
+    fn try_whitelist_batch() {
+        let mut context = get_context(false);
+        context.block_timestamp = 50;
+        testing_env!(context);
+        let mut contract = default_minter_init();
+        let results = contract.add_whitelist_batch(vec![
+            whitelist_entry("alice_near", 100, 5),
+            whitelist_entry("bob_near", 10, 5),
+            whitelist_entry("alice_near", 120, 2),
+        ]);
+        // The batch continues after an invalid item
+        assert_eq!(
+            results,
+            vec![
+                batch_result("alice_near", BatchStatus::Added),
+                batch_result("bob_near", BatchStatus::InvalidMintingStart),
+                batch_result("alice_near", BatchStatus::AlreadyExist),
+            ]
+        );
+        assert_eq!(
+            get_logs()[2..],
+            vec![
+                r#"EVENT_JSON:{"standard":"loterra_minter","version":"1.0.0","event":"whitelist_add","data":[{"account_id":"alice_near","minting_start":100,"minting_price":"1000","minting_limit":5}]}"#
+            ]
+        );
+
+        let results = contract.update_whitelist(vec![
+            whitelist_entry("alice_near", 120, 2),
+            whitelist_entry("bob_near", 150, 1),
+        ]);
+        assert_eq!(
+            results,
+            vec![
+                batch_result("alice_near", BatchStatus::Updated),
+                batch_result("bob_near", BatchStatus::Added),
+            ]
+        );
+        assert_eq!(contract.get_whitelist(0, 10).len(), 2);
+
+        let results = contract.remove_whitelist_batch(vec![
+            "bob_near".parse::<AccountId>().unwrap(),
+            "carol_near".parse::<AccountId>().unwrap(),
+        ]);
+        assert_eq!(
+            results,
+            vec![
+                batch_result("bob_near", BatchStatus::Removed),
+                batch_result("carol_near", BatchStatus::NotFound),
+            ]
+        );
+        assert_eq!(contract.get_whitelist(0, 10).len(), 1);
+    }
+
+    #[test]
+    fn try_update_whitelist_started_account_keep_start() {
+        let mut context = get_context(false);
+        testing_env!(context.clone());
+        let mut contract = default_minter_init();
+        contract.add_whitelist_batch(vec![whitelist_entry("alice_near", 100, 5)]);
+        context.block_timestamp = 150;
+        testing_env!(context);
+        // The limit of a started account can be updated as long as the start is unchanged
+        let results = contract.update_whitelist(vec![
+            whitelist_entry("alice_near", 100, 8),
+            whitelist_entry("alice_near", 120, 8),
+        ]);
+        assert_eq!(
+            results,
+            vec![
+                batch_result("alice_near", BatchStatus::Updated),
+                batch_result("alice_near", BatchStatus::InvalidMintingStart),
+            ]
+        );
+    }
+
+    #[test]
+    fn try_whitelist_batch_skipped_out_of_gas() {
+        let mut context = get_context(false);
+        testing_env!(context.clone());
+        let mut contract = default_minter_init();
+        context.prepaid_gas = Gas::from_gas(BATCH_GAS_RESERVE - 1);
+        testing_env!(context);
+        let results = contract.add_whitelist_batch(vec![whitelist_entry("alice_near", 100, 5)]);
+        assert_eq!(
+            results,
+            vec![batch_result("alice_near", BatchStatus::Skipped)]
+        );
+        assert!(contract.get_whitelist(0, 10).is_empty());
+    }
+
+    #[test]
+    fn admin_try_delete() {
+        let mut context = get_context(false);
+        context.signer_account_id = "admin_near".parse::<AccountId>().unwrap();
+        context.predecessor_account_id = "admin_near".parse::<AccountId>().unwrap();
+        testing_env!(context);
+        let mut contract = default_minter_init();
+        // Admin add alice_near account
+        contract.add_whitelist(
+            "alice_near".parse::<AccountId>().unwrap(),
+            env::block_timestamp().checked_add(100).unwrap(),
+            U128::from(1000),
+            5,
+        );
+        // Admin delete alice_near account
+        contract.delete_whitelist("alice_near".parse::<AccountId>().unwrap());
+
+        assert_eq!(contract.get_whitelist(0, 10), vec![]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn unauthorized_account_try_to_delete_accounts() {
+        let mut context = get_context(false);
+        context.signer_account_id = "admin_near".parse::<AccountId>().unwrap();
+        context.predecessor_account_id = "admin_near".parse::<AccountId>().unwrap();
+        testing_env!(context);
+        let mut contract = default_minter_init();
+        // Admin add alice_near account
+        contract.add_whitelist(
+            "alice_near".parse::<AccountId>().unwrap(),
+            env::block_timestamp().checked_add(100).unwrap(),
+            U128::from(1000),
+            5,
+        );
+        let mut context = get_context(false);
+        context.signer_account_id = "bob_near".parse::<AccountId>().unwrap();
+        context.predecessor_account_id = "bob_near".parse::<AccountId>().unwrap();
+        testing_env!(context);
+        // Admin delete alice_near account
+        contract.delete_whitelist("alice_near".parse::<AccountId>().unwrap());
+    }
+
+    #[test]
+    fn try_storage_deposit() {
+        let mut context = get_context(false);
+        context.signer_account_id = "alice_near".parse::<AccountId>().unwrap();
+        context.predecessor_account_id = "alice_near".parse::<AccountId>().unwrap();
+        // Attach minimum deposit amount to the sender
+        context.attached_deposit =
+            NearToken::from_yoctonear(U128::from(8_000_000_000_000_000_000_000).0);
+        testing_env!(context.clone());
+        let mut contract = default_minter_init();
+        // Deposit to the current signer account
+        contract.storage_deposit(None, None);
+        // Verify storage deposit
+        assert_eq!(
+            contract
+                .storage_balance_of(context.clone().signer_account_id)
+                .unwrap()
+                .total,
+            NearToken::from_yoctonear(8_000_000_000_000_000_000_000)
+        );
+
+        //let mut context = get_context(false);
+        // Attach minimum deposit amount to the sender
+        context.attached_deposit =
+            NearToken::from_yoctonear(U128::from(10_000_000_000_000_000_000_000).0);
+        testing_env!(context.clone());
+        let bob_account = "bob_near".parse::<AccountId>().unwrap();
+        // Deposit to the desired account
+        contract.storage_deposit(Some(bob_account.clone()), None);
+        // Verify Bob storage deposit
+        assert_eq!(
+            contract.storage_balance_of(bob_account).unwrap().total,
+            NearToken::from_yoctonear(10_000_000_000_000_000_000_000)
+        );
+        // Verify Alice account storage deposit remain the same
+        assert_eq!(
+            contract
+                .storage_balance_of(context.clone().signer_account_id)
+                .unwrap()
+                .total,
+            NearToken::from_yoctonear(8_000_000_000_000_000_000_000)
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn try_storage_deposit_less_than_authorized() {
+        let mut context = get_context(false);
+        // Attach less than minimum deposit amount to the sender
+        // min 8_000_000_000_000_000_000_000 attached 7_000_000_000_000_000_000_000
+        context.attached_deposit =
+            NearToken::from_yoctonear(U128::from(7_000_000_000_000_000_000_000).0);
+        testing_env!(context.clone());
+        let mut contract = default_minter_init();
+        // Deposit to the current signer account
+        contract.storage_deposit(None, None);
+    }
+
+    #[test]
+    fn try_storage_deposit_withdraw() {
+        let mut context = get_context(false);
+        context.signer_account_id = "alice_near".parse::<AccountId>().unwrap();
+        context.predecessor_account_id = "alice_near".parse::<AccountId>().unwrap();
+        // Attach minimum deposit amount to the sender
+        context.attached_deposit =
+            NearToken::from_yoctonear(U128::from(8_000_000_000_000_000_000_000).0);
+        testing_env!(context.clone());
+        let mut contract = default_minter_init();
+        // Deposit to the current signer account
+        contract.storage_deposit(None, None);
+        // Attach 1 yocto_near
+        context.attached_deposit = NearToken::from_yoctonear(ONE_YOCTO);
+        testing_env!(context.clone());
+        // Withdraw previously deposited amount
+        contract.storage_withdraw(None);
+        // // Verify storage deposit
+        // assert_eq!(contract.storage_balance_of(context.clone().signer_account_id).unwrap().total, "No account found");
+    }
+
+    #[test]
+    #[should_panic]
+    fn try_storage_deposit_withdraw_without_attaching_one_yocto() {
+        let mut context = get_context(false);
+        context.signer_account_id = "alice_near".parse::<AccountId>().unwrap();
+        context.predecessor_account_id = "alice_near".parse::<AccountId>().unwrap();
+        // Attach minimum deposit amount to the sender
+        context.attached_deposit =
+            NearToken::from_yoctonear(U128::from(8_000_000_000_000_000_000_000).0);
+        testing_env!(context.clone());
+        let mut contract = default_minter_init();
+        // Deposit to the current signer account
+        contract.storage_deposit(None, None);
+        // Withdraw previously deposited amount
+        contract.storage_withdraw(None);
+    }
+    #[test]
+    #[should_panic]
+    fn try_storage_deposit_withdraw_no_deposit_found() {
+        let mut context = get_context(false);
+        context.signer_account_id = "alice_near".parse::<AccountId>().unwrap();
+        context.predecessor_account_id = "alice_near".parse::<AccountId>().unwrap();
+        testing_env!(context.clone());
+        let mut contract = default_minter_init();
+        // Attach 1 yocto_near
+        context.attached_deposit = NearToken::from_yoctonear(ONE_YOCTO);
+        testing_env!(context.clone());
+        // Withdraw previously deposited amount
+        contract.storage_withdraw(None);
+    }
+
+    #[test]
+    fn try_mint_sending_ft_sale_not_started_yet() {
+        let mut context = get_context(false);
+        context.signer_account_id = "alice_near".parse::<AccountId>().unwrap();
+        context.predecessor_account_id = "usdc_near".parse::<AccountId>().unwrap();
+        context.attached_deposit =
+            NearToken::from_yoctonear(U128::from(8_000_000_000_000_000_000_000).0);
+        testing_env!(context.clone());
+        let mut contract = default_minter_init();
+        // Deposit Near for minting
+        contract.storage_deposit(Some(context.signer_account_id.clone()), None);
+        contract.ft_on_transfer(
+            context.signer_account_id,
+            U128::from(1000),
+            json!({
+                "mint_amount": 10
+            })
+            .to_string(),
+        );
+    }
+
+    #[test]
+    fn try_mint_sending_ft_private_sale_started_account_whitelisted() {
+        let mut context = get_context(false);
+        // Admin whitelist Alice
+        context.signer_account_id = "admin_near".parse::<AccountId>().unwrap();
+        context.predecessor_account_id = "admin_near".parse::<AccountId>().unwrap();
+        testing_env!(context.clone());
+        let mut contract = default_minter_init();
+        contract.add_whitelist(
+            "alice_near".parse::<AccountId>().unwrap(),
+            env::block_timestamp().checked_add(100).unwrap(),
+            U128::from(1000),
+            5,
+        );
+
+        // Alice try to participate the public sale
+        context.signer_account_id = "alice_near".parse::<AccountId>().unwrap();
+        context.predecessor_account_id = "usdc_near".parse::<AccountId>().unwrap();
+        context.attached_deposit =
+            NearToken::from_yoctonear(U128::from(40_350_000_000_000_000_000_000).0);
+        context.block_timestamp = 100;
+        testing_env!(context.clone());
+
+        // Deposit Near for minting
+        contract.storage_deposit(Some(context.signer_account_id.clone()), None);
+        // Mint 3 of 5
+        contract.ft_on_transfer(
+            context.signer_account_id.clone(),
+            U128::from(3000),
+            json!({
+                "mint_amount": 3
+            })
+            .to_string(),
+        );
+        // // Mint 5 of 5
+        contract.ft_on_transfer(
+            context.signer_account_id,
+            U128::from(2000),
+            json!({
+                "mint_amount": 2
+            })
+            .to_string(),
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "The address is not in the whitelist")]
+    fn try_mint_sending_ft_private_sale_started_account_not_whitelisted() {
+        let mut context = get_context(false);
+        context.signer_account_id = "alice_near".parse::<AccountId>().unwrap();
+        context.predecessor_account_id = "usdc_near".parse::<AccountId>().unwrap();
+        context.attached_deposit =
+            NearToken::from_yoctonear(U128::from(8_000_000_000_000_000_000_000).0);
+        context.block_timestamp = 100;
+        testing_env!(context.clone());
+        let mut contract = default_minter_init();
+        // Deposit Near for minting
+        contract.storage_deposit(Some(context.signer_account_id.clone()), None);
+        contract.ft_on_transfer(
+            context.signer_account_id,
+            U128::from(1000),
+            json!({
+                "mint_amount": 10
+            })
+            .to_string(),
+        );
+    }
+
+    fn whitelisted_alice_at(minting_start: Timestamp, block_timestamp: u64) -> (Minter, VMContext) {
+        let mut context = get_context(false);
+        // Admin whitelist Alice
+        context.signer_account_id = "admin_near".parse::<AccountId>().unwrap();
+        context.predecessor_account_id = "admin_near".parse::<AccountId>().unwrap();
+        testing_env!(context.clone());
+        let mut contract = default_minter_init();
+        contract.add_whitelist(
+            "alice_near".parse::<AccountId>().unwrap(),
+            minting_start,
+            U128::from(1000),
+            5,
+        );
+
+        context.signer_account_id = "alice_near".parse::<AccountId>().unwrap();
+        context.predecessor_account_id = "usdc_near".parse::<AccountId>().unwrap();
+        context.attached_deposit =
+            NearToken::from_yoctonear(U128::from(40_350_000_000_000_000_000_000).0);
+        context.block_timestamp = block_timestamp;
+        testing_env!(context.clone());
+        // Deposit Near for minting
+        contract.storage_deposit(Some(context.signer_account_id.clone()), None);
+        (contract, context)
+    }
+
+    /// Alice whitelisted from 150, the owner configures the sale in setup and the FT
+    /// contract is the caller at block_timestamp
+    fn configured_alice_at(
+        block_timestamp: u64,
+        setup: impl FnOnce(&mut Minter),
+    ) -> (Minter, VMContext) {
+        let (mut contract, mut context) = whitelisted_alice_at(150, 100);
+        context.predecessor_account_id = "admin_near".parse::<AccountId>().unwrap();
+        testing_env!(context.clone());
+        setup(&mut contract);
+        context.predecessor_account_id = "usdc_near".parse::<AccountId>().unwrap();
+        context.block_timestamp = block_timestamp;
+        testing_env!(context.clone());
+        (contract, context)
+    }
+
+    #[test]
+    #[should_panic(expected = "Wrong amount sent, whitelist minting price 2000 usdc_near")]
+    fn try_mint_sending_ft_private_sale_whitelisted_with_public_price() {
+        let (mut contract, context) = whitelisted_alice_at(100, 100);
+        // Public price is 100 but Alice whitelist price is 1000
+        contract.ft_on_transfer(
+            context.signer_account_id,
+            U128::from(200),
+            json!({
+                "mint_amount": 2
+            })
+            .to_string(),
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Private sale not started yet for this account, starts at 150")]
+    fn try_mint_sending_ft_private_sale_before_whitelist_minting_start() {
+        // Private sale started at 100 but Alice can only enter at 150
+        let (mut contract, context) = whitelisted_alice_at(150, 120);
+        contract.ft_on_transfer(
+            context.signer_account_id,
+            U128::from(1000),
+            json!({
+                "mint_amount": 1
+            })
+            .to_string(),
+        );
+    }
+
+    #[test]
+    fn try_mint_sending_ft_private_sale_at_whitelist_minting_start() {
+        let (mut contract, context) = whitelisted_alice_at(150, 150);
+        contract.ft_on_transfer(
+            context.signer_account_id.clone(),
+            U128::from(1000),
+            json!({
+                "mint_amount": 1
+            })
+            .to_string(),
+        );
+        assert_eq!(contract.get_minting_of(context.signer_account_id), 1);
+    }
+
+    #[test]
+    fn try_mint_sending_ft_public_sale_whitelisted_pays_public_price() {
+        let (mut contract, context) = whitelisted_alice_at(150, 200);
+        contract.ft_on_transfer(
+            context.signer_account_id.clone(),
+            U128::from(100),
+            json!({
+                "mint_amount": 1
+            })
+            .to_string(),
+        );
+        assert_eq!(contract.get_minting_of(context.signer_account_id), 1);
+    }
+
+    fn dutch_auction_alice_at(block_timestamp: u64, rebate: bool) -> (Minter, VMContext) {
+        configured_alice_at(block_timestamp, |contract| {
+            contract.set_dutch_auction(Some(DutchAuction {
+                start_price: U128::from(1000),
+                floor_price: U128::from(400),
+                price_step: U128::from(100),
+                step_interval: 10,
+                rebate,
+            }))
+        })
+    }
+
+    #[test]
+    fn try_get_public_sale_price_dutch_auction() {
+        let (contract, mut context) = dutch_auction_alice_at(100, false);
+        // Before the public sale the auction is at the start price
+        assert_eq!(
+            contract.get_public_sale_price(),
+            PublicSalePrice {
+                current_price: U128::from(1000),
+                next_price: Some(U128::from(900)),
+                next_price_at: Some(210),
+            }
+        );
+        context.block_timestamp = 235;
+        testing_env!(context.clone());
+        assert_eq!(
+            contract.get_public_sale_price(),
+            PublicSalePrice {
+                current_price: U128::from(700),
+                next_price: Some(U128::from(600)),
+                next_price_at: Some(240),
+            }
+        );
+        context.block_timestamp = 1_000;
+        testing_env!(context);
+        assert_eq!(
+            contract.get_public_sale_price(),
+            PublicSalePrice {
+                current_price: U128::from(400),
+                next_price: None,
+                next_price_at: None,
+            }
+        );
+    }
+
+    #[test]
+    fn try_mint_sending_ft_dutch_auction_overpayment() {
+        let (mut contract, context) = dutch_auction_alice_at(225, false);
+        // Current price is 800, Alice sent the start price
+        contract.ft_on_transfer(
+            context.signer_account_id.clone(),
+            U128::from(2000),
+            json!({
+                "mint_amount": 2
+            })
+            .to_string(),
+        );
+        assert!(get_logs().iter().any(|log| log.contains(
+            r#""event":"purchase_started","data":[{"buyer_id":"alice_near","token_ids":["4999","4998"],"payment_token":"usdc_near","amount":"1600"}]"#
+        )));
+
+        set_promise_result(context.clone(), PromiseResult::Successful(vec![]));
+        let refund = contract.mint_result(
+            vec!["4999".to_string(), "4998".to_string()],
+            context.signer_account_id.clone(),
+            U128::from(2 * MINT_STORAGE_COST),
+            U128::from(0),
+            U128::from(1600),
+            U128::from(400),
+            None,
+            Some("usdc_near".parse::<AccountId>().unwrap()),
+            None,
+        );
+        // The overpayment is returned as unused amount to the FT contract
+        assert_eq!(refund, U128::from(400));
+
+        contract.ft_on_transfer(
+            context.signer_account_id.clone(),
+            U128::from(2000),
+            json!({
+                "mint_amount": 2
+            })
+            .to_string(),
+        );
+        set_promise_result(context.clone(), PromiseResult::Failed);
+        let refund = contract.mint_result(
+            vec!["4997".to_string(), "4996".to_string()],
+            context.signer_account_id,
+            U128::from(2 * MINT_STORAGE_COST),
+            U128::from(0),
+            U128::from(1600),
+            U128::from(400),
+            None,
+            Some("usdc_near".parse::<AccountId>().unwrap()),
+            None,
+        );
+        assert_eq!(refund, U128::from(2000));
+    }
+
+    #[test]
+    #[should_panic(expected = "Not enough amount sent, current auction price 1600 usdc_near")]
+    fn try_mint_sending_ft_dutch_auction_underpayment() {
+        let (mut contract, context) = dutch_auction_alice_at(225, false);
+        contract.ft_on_transfer(
+            context.signer_account_id,
+            U128::from(1400),
+            json!({
+                "mint_amount": 2
+            })
+            .to_string(),
+        );
+    }
+
+    fn auction_buy(
+        contract: &mut Minter,
+        context: &mut VMContext,
+        block_timestamp: u64,
+        paid: u128,
+    ) {
+        context.predecessor_account_id = "usdc_near".parse::<AccountId>().unwrap();
+        context.block_timestamp = block_timestamp;
+        testing_env!(context.clone());
+        contract.ft_on_transfer(
+            context.signer_account_id.clone(),
+            U128::from(paid),
+            json!({
+                "mint_amount": 1
+            })
+            .to_string(),
+        );
+        let token_id = get_logs()
+            .iter()
+            .find_map(|log| {
+                log.split(r#""token_ids":[""#)
+                    .nth(1)
+                    .map(|ids| ids.split('"').next().unwrap().to_string())
+            })
+            .unwrap();
+        set_promise_result(context.clone(), PromiseResult::Successful(vec![]));
+        contract.mint_result(
+            vec![token_id],
+            context.signer_account_id.clone(),
+            U128::from(MINT_STORAGE_COST),
+            U128::from(0),
+            U128::from(paid),
+            U128::from(0),
+            Some(U128::from(paid)),
+            Some("usdc_near".parse::<AccountId>().unwrap()),
+            None,
+        );
+    }
+
+    #[test]
+    fn try_claim_rebate_at_clearing_price() {
+        let (mut contract, mut context) = dutch_auction_alice_at(225, true);
+        let alice = context.signer_account_id.clone();
+        let usdc = "usdc_near".parse::<AccountId>().unwrap();
+        // Alice buys at 800 then at 500
+        auction_buy(&mut contract, &mut context, 225, 800);
+        auction_buy(&mut contract, &mut context, 255, 500);
+        assert_eq!(
+            contract.get_auction_rebate(alice.clone(), usdc.clone()),
+            U128::from(0)
+        );
+        assert_eq!(contract.get_proceeds(usdc.clone()), U128::from(0));
+
+        context.predecessor_account_id = "admin_near".parse::<AccountId>().unwrap();
+        testing_env!(context.clone());
+        contract.close_auction();
+        assert_eq!(contract.get_clearing_price(), Some(U128::from(500)));
+        assert_eq!(
+            get_logs(),
+            vec![
+                r#"EVENT_JSON:{"standard":"loterra_minter","version":"1.0.0","event":"auction_close","data":[{"clearing_price":"500"}]}"#
+            ]
+        );
+        assert_eq!(
+            contract.get_auction_rebate(alice.clone(), usdc.clone()),
+            U128::from(300)
+        );
+        // The proceeds are kept at the clearing price
+        assert_eq!(contract.get_proceeds(usdc.clone()), U128::from(1000));
+
+        context.predecessor_account_id = alice.clone();
+        testing_env!(context.clone());
+        contract.claim_rebate(usdc.clone());
+        assert_eq!(
+            contract.get_auction_rebate(alice.clone(), usdc.clone()),
+            U128::from(0)
+        );
+
+        // The purchase is restored when the rebate transfer failed
+        context.predecessor_account_id = context.current_account_id.clone();
+        set_promise_result(context.clone(), PromiseResult::Failed);
+        contract.on_rebate_claimed(
+            alice.clone(),
+            usdc.clone(),
+            Purchase {
+                packs: 2,
+                paid: U128::from(1300),
+            },
+        );
+        assert_eq!(contract.get_auction_rebate(alice, usdc), U128::from(300));
+    }
+
+    #[test]
+    #[should_panic(expected = "No rebate to claim")]
+    fn try_claim_rebate_auction_not_closed() {
+        let (mut contract, mut context) = dutch_auction_alice_at(225, true);
+        auction_buy(&mut contract, &mut context, 225, 800);
+        context.predecessor_account_id = context.signer_account_id.clone();
+        testing_env!(context);
+        contract.claim_rebate("usdc_near".parse::<AccountId>().unwrap());
+    }
+
+    #[test]
+    #[should_panic(expected = "The Dutch auction is already closed")]
+    fn try_mint_sending_ft_dutch_auction_closed() {
+        let (mut contract, mut context) = dutch_auction_alice_at(225, true);
+        auction_buy(&mut contract, &mut context, 225, 800);
+        context.predecessor_account_id = "admin_near".parse::<AccountId>().unwrap();
+        testing_env!(context.clone());
+        contract.close_auction();
+        auction_buy(&mut contract, &mut context, 255, 500);
+    }
+
+    #[test]
+    #[should_panic(expected = "NEAR payment is not available during the Dutch auction")]
+    fn try_nft_pack_buy_with_near_dutch_auction() {
+        let (mut contract, mut context) = dutch_auction_alice_at(225, false);
+        context.predecessor_account_id = "admin_near".parse::<AccountId>().unwrap();
+        testing_env!(context.clone());
+        contract.set_near_minting_price(Some(U128::from(ONE_NEAR)));
+        context.predecessor_account_id = context.signer_account_id.clone();
+        context.attached_deposit = NearToken::from_yoctonear(ONE_NEAR);
+        testing_env!(context);
+        contract.nft_pack_buy(1, None, None);
+    }
+
+    #[test]
+    fn try_close_auction_after_sale_end() {
+        let (mut contract, mut context) = dutch_auction_alice_at(225, true);
+        auction_buy(&mut contract, &mut context, 225, 800);
+        context.predecessor_account_id = "admin_near".parse::<AccountId>().unwrap();
+        testing_env!(context.clone());
+        contract.set_public_sale_end(Some(300));
+
+        // Any account closes the auction once the sale ended
+        context.predecessor_account_id = context.signer_account_id.clone();
+        context.block_timestamp = 300;
+        testing_env!(context);
+        contract.close_auction();
+        assert_eq!(contract.get_clearing_price(), Some(U128::from(800)));
+    }
+
+    #[test]
+    #[should_panic(expected = "NFT pack mints are still pending")]
+    fn try_close_auction_mint_pending() {
+        let (mut contract, mut context) = dutch_auction_alice_at(225, true);
+        auction_buy(&mut contract, &mut context, 225, 800);
+        // The mint at 500 is not resolved yet, its price can't be left out of the clearing price
+        context.block_timestamp = 255;
+        testing_env!(context.clone());
+        contract.ft_on_transfer(
+            context.signer_account_id.clone(),
+            U128::from(500),
+            json!({
+                "mint_amount": 1
+            })
+            .to_string(),
+        );
+        context.predecessor_account_id = "admin_near".parse::<AccountId>().unwrap();
+        testing_env!(context);
+        contract.close_auction();
+    }
+
+    #[test]
+    #[should_panic(expected = "Owner's method")]
+    fn try_close_auction_before_sale_end() {
+        let (mut contract, mut context) = dutch_auction_alice_at(225, true);
+        context.predecessor_account_id = "admin_near".parse::<AccountId>().unwrap();
+        testing_env!(context.clone());
+        contract.set_public_sale_end(Some(300));
+        context.predecessor_account_id = context.signer_account_id.clone();
+        testing_env!(context);
+        contract.close_auction();
+    }
+
+    #[test]
+    fn try_set_dutch_auction_event() {
+        let context = get_context(false);
+        testing_env!(context);
+        let mut contract = default_minter_init();
+        contract.set_dutch_auction(Some(DutchAuction {
+            start_price: U128::from(1000),
+            floor_price: U128::from(400),
+            price_step: U128::from(100),
+            step_interval: 10,
+            rebate: true,
+        }));
+        contract.set_dutch_auction(None);
+        assert_eq!(
+            get_logs()[2..],
+            vec![
+                r#"EVENT_JSON:{"standard":"loterra_minter","version":"1.0.0","event":"dutch_auction_set","data":[{"dutch_auction":{"start_price":"1000","floor_price":"400","price_step":"100","step_interval":10,"rebate":true}}]}"#,
+                r#"EVENT_JSON:{"standard":"loterra_minter","version":"1.0.0","event":"dutch_auction_set","data":[{"dutch_auction":null}]}"#,
+            ]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "The start price should not be lower than the floor price")]
+    fn try_set_dutch_auction_floor_above_start() {
+        let context = get_context(false);
+        testing_env!(context);
+        let mut contract = default_minter_init();
+        contract.set_dutch_auction(Some(DutchAuction {
+            start_price: U128::from(100),
+            floor_price: U128::from(400),
+            price_step: U128::from(100),
+            step_interval: 10,
+            rebate: false,
+        }));
+    }
+
+    fn og_tier_alice_at(block_timestamp: u64) -> (Minter, VMContext) {
+        configured_alice_at(block_timestamp, |contract| {
+            contract.set_tier("og".to_string(), 120, 190, U128::from(500), 2);
+            contract.add_tier_members(
+                "og".to_string(),
+                vec!["alice_near".parse::<AccountId>().unwrap()],
+            );
+        })
+    }
+
+    #[test]
+    fn try_set_tier_and_members_events() {
+        let context = get_context(false);
+        testing_env!(context);
+        let mut contract = default_minter_init();
+        let alice = "alice_near".parse::<AccountId>().unwrap();
+        contract.set_tier("og".to_string(), 120, 190, U128::from(500), 2);
+        contract.add_tier_members("og".to_string(), vec![alice.clone()]);
+        assert_eq!(
+            get_logs()[2..],
+            vec![
+                r#"EVENT_JSON:{"standard":"loterra_minter","version":"1.0.0","event":"tier_set","data":[{"tier":"og","minting_start":120,"minting_end":190,"minting_price":"500","minting_limit":2}]}"#,
+                r#"EVENT_JSON:{"standard":"loterra_minter","version":"1.0.0","event":"tier_member_add","data":[{"account_id":"alice_near","tier":"og"}]}"#
+            ]
+        );
+        assert_eq!(contract.get_tier_of(alice), Some("og".to_string()));
+    }
+
+    #[test]
+    fn try_mint_sending_ft_tier_terms_take_precedence() {
+        let (mut contract, mut context) = og_tier_alice_at(130);
+        // Alice whitelist starts at 150 with price 1000, the tier starts at 120 with price 500
+        contract.ft_on_transfer(
+            context.signer_account_id.clone(),
+            U128::from(500),
+            json!({
+                "mint_amount": 1
+            })
+            .to_string(),
+        );
+        assert_eq!(
+            contract.get_minting_of(context.signer_account_id.clone()),
+            1
+        );
+
+        // Updating the tier updates every member
+        context.predecessor_account_id = "admin_near".parse::<AccountId>().unwrap();
+        testing_env!(context.clone());
+        contract.set_tier("og".to_string(), 120, 190, U128::from(300), 2);
+        assert_eq!(
+            contract.get_tiers(0, 10),
+            vec![(
+                "og".to_string(),
+                WhitelistTier {
+                    minting_start: 120,
+                    minting_end: 190,
+                    minting_price: U128::from(300),
+                    minting_limit: 2,
+                }
+            )]
+        );
+        context.predecessor_account_id = "usdc_near".parse::<AccountId>().unwrap();
+        testing_env!(context.clone());
+        contract.ft_on_transfer(
+            context.signer_account_id.clone(),
+            U128::from(300),
+            json!({
+                "mint_amount": 1
+            })
+            .to_string(),
+        );
+        assert_eq!(contract.get_minting_of(context.signer_account_id), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "Private sale ended for this account, ended at 190")]
+    fn try_mint_sending_ft_tier_ended() {
+        let (mut contract, context) = og_tier_alice_at(195);
+        contract.ft_on_transfer(
+            context.signer_account_id,
+            U128::from(500),
+            json!({
+                "mint_amount": 1
+            })
+            .to_string(),
+        );
+    }
+
+    #[test]
+    fn try_mint_sending_ft_tier_removed_fall_back_to_whitelist() {
+        let (mut contract, mut context) = og_tier_alice_at(195);
+        context.predecessor_account_id = "admin_near".parse::<AccountId>().unwrap();
+        testing_env!(context.clone());
+        contract.remove_tier_members(vec![context.signer_account_id.clone()]);
+        context.predecessor_account_id = "usdc_near".parse::<AccountId>().unwrap();
+        testing_env!(context.clone());
+        contract.ft_on_transfer(
+            context.signer_account_id.clone(),
+            U128::from(1000),
+            json!({
+                "mint_amount": 1
+            })
+            .to_string(),
+        );
+        assert_eq!(contract.get_minting_of(context.signer_account_id), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Tier not found")]
+    fn try_add_tier_members_unknown_tier() {
+        let context = get_context(false);
+        testing_env!(context);
+        let mut contract = default_minter_init();
+        contract.add_tier_members(
+            "og".to_string(),
+            vec!["alice_near".parse::<AccountId>().unwrap()],
+        );
+    }
+
+    /// Merkle whitelist of alice_near (limit 3, price 700) and bob_near (limit 1, price 500),
+    /// returns the root and the proof of alice_near
+    fn merkle_whitelist() -> (Base58CryptoHash, Vec<Base58CryptoHash>) {
+        let alice = merkle_leaf(
+            &"alice_near".parse::<AccountId>().unwrap(),
+            3,
+            U128::from(700),
+        );
+        let bob = merkle_leaf(
+            &"bob_near".parse::<AccountId>().unwrap(),
+            1,
+            U128::from(500),
+        );
+        let pair = if alice <= bob {
+            [alice, bob].concat()
+        } else {
+            [bob, alice].concat()
+        };
+        let root: CryptoHash = env::sha256(&pair).try_into().unwrap();
+        (root.into(), vec![bob.into()])
+    }
+
+    fn merkle_alice_at(block_timestamp: u64) -> (Minter, VMContext, Vec<Base58CryptoHash>) {
+        let (root, proof) = merkle_whitelist();
+        let (contract, context) = configured_alice_at(block_timestamp, |contract| {
+            contract.set_merkle_root(Some(root));
+            assert_eq!(contract.get_merkle_root(), Some(root));
+        });
+        (contract, context, proof)
+    }
+
+    #[test]
+    fn try_mint_sending_ft_merkle_proof() {
+        let (mut contract, context, proof) = merkle_alice_at(120);
+        // The proven terms take precedence over the whitelist state starting at 150
+        contract.ft_on_transfer(
+            context.signer_account_id.clone(),
+            U128::from(2100),
+            json!({
+                "mint_amount": 3,
+                "merkle_proof": {
+                    "minting_limit": 3,
+                    "minting_price": "700",
+                    "proof": proof
+                }
+            })
+            .to_string(),
+        );
+        assert_eq!(contract.get_minting_of(context.signer_account_id), 3);
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid Merkle proof")]
+    fn try_mint_sending_ft_merkle_proof_wrong_terms() {
+        let (mut contract, context, proof) = merkle_alice_at(120);
+        contract.ft_on_transfer(
+            context.signer_account_id,
+            U128::from(500),
+            json!({
+                "mint_amount": 1,
+                "merkle_proof": {
+                    "minting_limit": 3,
+                    "minting_price": "500",
+                    "proof": proof
+                }
+            })
+            .to_string(),
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Out of mint")]
+    fn try_mint_sending_ft_merkle_proof_out_of_mint() {
+        let (mut contract, context, proof) = merkle_alice_at(120);
+        let msg = json!({
+            "mint_amount": 2,
+            "merkle_proof": {
+                "minting_limit": 3,
+                "minting_price": "700",
+                "proof": proof
+            }
+        })
+        .to_string();
+        contract.ft_on_transfer(
+            context.signer_account_id.clone(),
+            U128::from(1400),
+            msg.clone(),
+        );
+        contract.ft_on_transfer(context.signer_account_id, U128::from(1400), msg);
+    }
+
+    fn voucher_keypair() -> ed25519_dalek::SigningKey {
+        ed25519_dalek::SigningKey::from_bytes(&[7; 32])
+    }
+
+    fn signed_voucher(price: u128, expiry: Timestamp, nonce: u64) -> SignedVoucher {
+        signed_voucher_for(env::current_account_id(), price, expiry, nonce)
+    }
+
+    fn signed_voucher_for(
+        minter_id: AccountId,
+        price: u128,
+        expiry: Timestamp,
+        nonce: u64,
+    ) -> SignedVoucher {
+        use ed25519_dalek::Signer;
+        let voucher = Voucher {
+            account_id: "alice_near".parse::<AccountId>().unwrap(),
+            max_amount: 2,
+            price: U128::from(price),
+            expiry,
+            nonce,
+        };
+        let message = borsh::to_vec(&(minter_id, &voucher)).unwrap();
+        let signature = voucher_keypair().sign(&message);
+        SignedVoucher {
+            voucher,
+            signature: Base64VecU8::from(signature.to_bytes().to_vec()),
+        }
+    }
+
+    fn voucher_alice_at(block_timestamp: u64) -> (Minter, VMContext) {
+        let public_key = [
+            vec![0],
+            voucher_keypair().verifying_key().to_bytes().to_vec(),
+        ]
+        .concat();
+        configured_alice_at(block_timestamp, |contract| {
+            contract.set_voucher_public_key(Some(PublicKey::try_from(public_key).unwrap()))
+        })
+    }
+
+    fn voucher_mint(
+        contract: &mut Minter,
+        context: &VMContext,
+        amount: u128,
+        voucher: SignedVoucher,
+    ) {
+        contract.ft_on_transfer(
+            context.signer_account_id.clone(),
+            U128::from(amount),
+            json!({
+                "mint_amount": 2,
+                "voucher": voucher
+            })
+            .to_string(),
+        );
+    }
+
+    #[test]
+    fn try_mint_sending_ft_voucher() {
+        let (mut contract, context) = voucher_alice_at(120);
+        // The voucher allows Alice to mint before her whitelist start at her voucher price
+        voucher_mint(&mut contract, &context, 1200, signed_voucher(600, 130, 1));
+        assert_eq!(
+            contract.get_minting_of(context.signer_account_id.clone()),
+            2
+        );
+        assert!(contract.is_voucher_used(1));
+        // A new voucher is a new allocation
+        voucher_mint(&mut contract, &context, 1200, signed_voucher(600, 130, 2));
+        assert_eq!(contract.get_minting_of(context.signer_account_id), 4);
+    }
+
+    #[test]
+    #[should_panic(expected = "Voucher already used")]
+    fn try_mint_sending_ft_voucher_reused() {
+        let (mut contract, context) = voucher_alice_at(120);
+        voucher_mint(&mut contract, &context, 1200, signed_voucher(600, 130, 1));
+        voucher_mint(&mut contract, &context, 1200, signed_voucher(600, 130, 1));
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid voucher signature")]
+    fn try_mint_sending_ft_voucher_tampered() {
+        let (mut contract, context) = voucher_alice_at(120);
+        let mut voucher = signed_voucher(600, 130, 1);
+        voucher.voucher.price = U128::from(1);
+        voucher_mint(&mut contract, &context, 2, voucher);
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid voucher signature")]
+    fn try_mint_sending_ft_voucher_other_minter() {
+        let (mut contract, context) = voucher_alice_at(120);
+        // A voucher signed for another launch can't be replayed
+        let voucher = signed_voucher_for("minter.testnet".parse().unwrap(), 600, 130, 1);
+        voucher_mint(&mut contract, &context, 1200, voucher);
+    }
+
+    #[test]
+    #[should_panic(expected = "Voucher expired")]
+    fn try_mint_sending_ft_voucher_expired() {
+        let (mut contract, context) = voucher_alice_at(140);
+        voucher_mint(&mut contract, &context, 1200, signed_voucher(600, 130, 1));
+    }
+
+    fn set_promise_result(context: VMContext, result: PromiseResult) {
+        testing_env!(
+            context,
+            test_vm_config(),
+            RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![result]
+        );
+    }
+
+    #[test]
+    fn try_mint_result_success_reduce_supply() {
+        let (mut contract, context) = whitelisted_alice_at(150, 200);
+        contract.ft_on_transfer(
+            context.signer_account_id.clone(),
+            U128::from(300),
+            json!({
+                "mint_amount": 3
+            })
+            .to_string(),
+        );
+        assert_eq!(contract.get_nft_pack_supply(), 4997);
+        assert_eq!(contract.get_pending_mints(), 3);
+        set_promise_result(context.clone(), PromiseResult::Successful(vec![]));
+        let refund = contract.mint_result(
+            vec!["4999".to_string(), "4998".to_string(), "4997".to_string()],
+            context.signer_account_id.clone(),
+            U128::from(3 * MINT_STORAGE_COST),
+            U128::from(1_000),
+            U128::from(300),
+            U128::from(0),
+            None,
+            Some("usdc_near".parse::<AccountId>().unwrap()),
+            None,
+        );
+
+        assert_eq!(refund, U128::from(0));
+        assert_eq!(contract.get_nft_pack_supply(), 4997);
+        assert_eq!(contract.get_pending_mints(), 0);
+        assert_eq!(
+            contract.get_minting_of(context.signer_account_id.clone()),
+            3
+        );
+        // The storage refunded by the NFT pack contract is credited back
+        assert_eq!(
+            contract
+                .storage_balance_of(context.signer_account_id)
+                .unwrap()
+                .total,
+            NearToken::from_yoctonear(
+                40_350_000_000_000_000_000_000 - 3 * MINT_STORAGE_COST + 1_000
+            )
+        );
+    }
+
+    #[test]
+    fn try_set_mint_storage() {
+        let context = get_context(false);
+        testing_env!(context);
+        let mut contract = default_minter_init();
+        let cost = U128::from(10_000_000_000_000_000_000_000);
+        contract.set_mint_storage(cost, 600);
+        assert_eq!(
+            get_logs()[2..],
+            vec![
+                r#"EVENT_JSON:{"standard":"loterra_minter","version":"1.0.0","event":"mint_storage_set","data":[{"mint_storage_cost":"10000000000000000000000","mint_storage_usage":600}]}"#
+            ]
+        );
+        assert_eq!(contract.get_mint_storage(), (cost, 600));
+        assert_eq!(
+            contract.storage_balance_bounds().min,
+            NearToken::from_yoctonear(cost.0)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "The mint storage cost should cover the mint storage usage")]
+    fn try_set_mint_storage_below_usage() {
+        let context = get_context(false);
+        testing_env!(context);
+        let mut contract = default_minter_init();
+        contract.set_mint_storage(U128::from(1), 600);
+    }
+
+    #[test]
+    fn try_mint_result_failed_rollback_and_refund() {
+        let (mut contract, context) = whitelisted_alice_at(150, 200);
+        contract.ft_on_transfer(
+            context.signer_account_id.clone(),
+            U128::from(300),
+            json!({
+                "mint_amount": 3
+            })
+            .to_string(),
+        );
+        assert_eq!(
+            contract
+                .storage_balance_of(context.signer_account_id.clone())
+                .unwrap()
+                .total,
+            NearToken::from_yoctonear(40_350_000_000_000_000_000_000 - 3 * MINT_STORAGE_COST)
+        );
+        set_promise_result(context.clone(), PromiseResult::Failed);
+        let refund = contract.mint_result(
+            vec!["4999".to_string(), "4998".to_string(), "4997".to_string()],
+            context.signer_account_id.clone(),
+            U128::from(3 * MINT_STORAGE_COST),
+            U128::from(0),
+            U128::from(300),
+            U128::from(0),
+            None,
+            Some("usdc_near".parse::<AccountId>().unwrap()),
+            None,
+        );
+
+        // The whole payment is returned as unused amount to the FT contract
+        assert_eq!(refund, U128::from(300));
+        assert_eq!(contract.get_nft_pack_supply(), 5000);
+        assert_eq!(contract.get_pending_mints(), 0);
+        assert_eq!(
+            contract.get_minting_of(context.signer_account_id.clone()),
+            0
+        );
+        assert_eq!(
+            contract
+                .storage_balance_of(context.signer_account_id)
+                .unwrap()
+                .total,
+            NearToken::from_yoctonear(40_350_000_000_000_000_000_000)
+        );
+    }
+
+    #[test]
+    fn try_mint_result_failed_releases_voucher() {
+        let (mut contract, context) = voucher_alice_at(120);
+        voucher_mint(&mut contract, &context, 1200, signed_voucher(600, 130, 1));
+        assert!(contract.is_voucher_used(1));
+
+        set_promise_result(context.clone(), PromiseResult::Failed);
+        contract.mint_result(
+            vec!["4999".to_string(), "4998".to_string()],
+            context.signer_account_id.clone(),
+            U128::from(2 * MINT_STORAGE_COST),
+            U128::from(0),
+            U128::from(1200),
+            U128::from(0),
+            None,
+            Some("usdc_near".parse::<AccountId>().unwrap()),
+            Some(1),
+        );
+        // The voucher is not burnt by the failed mint
+        assert!(!contract.is_voucher_used(1));
+        testing_env!(context.clone());
+        voucher_mint(&mut contract, &context, 1200, signed_voucher(600, 130, 1));
+    }
+
+    #[test]
+    fn try_reserve_token_ids_same_block() {
+        let context = get_context(false);
+        testing_env!(context);
+        let mut contract = default_minter_init();
+        // Two purchases in the same block never share a token id
+        assert_eq!(
+            contract.internal_reserve_token_ids(2),
+            vec!["4999".to_string(), "4998".to_string()]
+        );
+        assert_eq!(
+            contract.internal_reserve_token_ids(1),
+            vec!["4997".to_string()]
+        );
+        assert_eq!(contract.get_nft_pack_supply(), 4997);
+        assert_eq!(contract.get_pending_mints(), 3);
+    }
+
+    #[test]
+    fn try_release_token_ids_reserved_again() {
+        let context = get_context(false);
+        testing_env!(context);
+        let mut contract = default_minter_init();
+        let first = contract.internal_reserve_token_ids(2);
+        let second = contract.internal_reserve_token_ids(1);
+        // The first purchase failed, its token ids go back to the supply
+        contract.internal_release_token_ids(&first);
+        assert_eq!(contract.get_nft_pack_supply(), 4999);
+
+        let third = contract.internal_reserve_token_ids(3);
+        assert_eq!(
+            third,
+            vec!["4998".to_string(), "4999".to_string(), "4996".to_string()]
+        );
+        assert!(!third.contains(&second[0]));
+    }
+
+    #[test]
+    #[should_panic(expected = "Supply limit reached. Left 1 NFT pack")]
+    fn try_reserve_more_than_supply() {
+        let context = get_context(false);
+        testing_env!(context);
+        let mut contract = default_minter_init();
+        contract.internal_reserve_token_ids(4999);
+        contract.internal_reserve_token_ids(2);
+    }
+
+    #[test]
+    fn try_payment_token_decimals() {
+        let usdc = PaymentToken {
+            decimals: 6,
+            enabled: true,
+            paused: false,
+        };
+        let dai = PaymentToken {
+            decimals: 18,
+            enabled: true,
+            paused: false,
+        };
+        // 1.5 USD
+        assert_eq!(usdc.to_token_amount(1_500_000), 1_500_000);
+        assert_eq!(dai.to_token_amount(1_500_000), 1_500_000_000_000_000_000);
+        assert_eq!(dai.to_price(1_500_000_000_000_000_000), 1_500_000);
+    }
+
+    #[test]
+    fn try_mint_sending_dai_added_by_admin() {
+        let (mut contract, mut context) = whitelisted_alice_at(150, 200);
+        context.signer_account_id = "admin_near".parse::<AccountId>().unwrap();
+        context.predecessor_account_id = "admin_near".parse::<AccountId>().unwrap();
+        testing_env!(context.clone());
+        contract.add_payment_token("dai_near".parse::<AccountId>().unwrap(), 18);
+
+        context.signer_account_id = "alice_near".parse::<AccountId>().unwrap();
+        context.predecessor_account_id = "dai_near".parse::<AccountId>().unwrap();
+        testing_env!(context.clone());
+        contract.ft_on_transfer(
+            context.signer_account_id.clone(),
+            U128::from(200_000_000_000_000),
+            json!({
+                "mint_amount": 2
+            })
+            .to_string(),
+        );
+        assert_eq!(contract.get_minting_of(context.signer_account_id), 2);
+    }
+
+    #[test]
+    fn try_payment_token_events() {
+        let context = get_context(false);
+        testing_env!(context.clone());
+        let mut contract = default_minter_init();
+        testing_env!(context);
+        let dai = "dai_near".parse::<AccountId>().unwrap();
+        contract.add_payment_token(dai.clone(), 18);
+        contract.set_payment_token_enabled(dai, false);
+        contract.set_near_minting_price(Some(U128::from(ONE_NEAR)));
+        assert_eq!(
+            get_logs(),
+            vec![
+                r#"EVENT_JSON:{"standard":"loterra_minter","version":"1.0.0","event":"payment_token_add","data":[{"token_account_id":"dai_near","decimals":18}]}"#,
+                r#"EVENT_JSON:{"standard":"loterra_minter","version":"1.0.0","event":"payment_token_enabled_set","data":[{"token_account_id":"dai_near","enabled":false}]}"#,
+                r#"EVENT_JSON:{"standard":"loterra_minter","version":"1.0.0","event":"near_minting_price_set","data":[{"near_minting_price":"1000000000000000000000000"}]}"#,
+            ]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Payment token decimals below 6")]
+    fn try_add_payment_token_too_few_decimals() {
+        let context = get_context(false);
+        testing_env!(context);
+        let mut contract = default_minter_init();
+        // A price of 0.000001 would be paid 0 with 2 decimals
+        contract.add_payment_token("eurs_near".parse::<AccountId>().unwrap(), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "Payment token decimals above 24")]
+    fn try_add_payment_token_too_many_decimals() {
+        let context = get_context(false);
+        testing_env!(context);
+        let mut contract = default_minter_init();
+        contract.add_payment_token("dai_near".parse::<AccountId>().unwrap(), 45);
+    }
+
+    #[test]
+    #[should_panic(expected = "Payment token already added")]
+    fn try_add_payment_token_already_added() {
+        let context = get_context(false);
+        testing_env!(context);
+        let mut contract = default_minter_init();
+        contract.pause(PauseScope::PaymentToken(
+            "usdc_near".parse::<AccountId>().unwrap(),
+        ));
+        // Re-adding would unpause the token and change the decimals of the purchases
+        contract.add_payment_token("usdc_near".parse::<AccountId>().unwrap(), 18);
+    }
+
+    #[test]
+    #[should_panic(expected = "Payment token disabled")]
+    fn try_mint_sending_disabled_payment_token() {
+        let (mut contract, mut context) = whitelisted_alice_at(150, 200);
+        context.signer_account_id = "admin_near".parse::<AccountId>().unwrap();
+        context.predecessor_account_id = "admin_near".parse::<AccountId>().unwrap();
+        testing_env!(context.clone());
+        contract.set_payment_token_enabled("usdt_near".parse::<AccountId>().unwrap(), false);
+        assert_eq!(
+            contract.get_payment_tokens()[1],
+            (
+                "usdt_near".parse::<AccountId>().unwrap(),
+                PaymentToken {
+                    decimals: 6,
+                    enabled: false,
+                    paused: false
+                }
+            )
+        );
+
+        context.signer_account_id = "alice_near".parse::<AccountId>().unwrap();
+        context.predecessor_account_id = "usdt_near".parse::<AccountId>().unwrap();
+        testing_env!(context.clone());
+        contract.ft_on_transfer(
+            context.signer_account_id,
+            U128::from(100),
+            json!({
+                "mint_amount": 1
+            })
+            .to_string(),
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Only allowed NF contracts can call this message")]
+    fn try_mint_sending_unknown_payment_token() {
+        let (mut contract, mut context) = whitelisted_alice_at(150, 200);
+        context.predecessor_account_id = "fake_usdc_near".parse::<AccountId>().unwrap();
+        testing_env!(context.clone());
+        contract.ft_on_transfer(
+            context.signer_account_id,
+            U128::from(100),
+            json!({
+                "mint_amount": 1
+            })
+            .to_string(),
+        );
+    }
+
+    fn near_price_alice_at(block_timestamp: u64) -> (Minter, VMContext) {
+        let (contract, mut context) = configured_alice_at(block_timestamp, |contract| {
+            contract.set_near_minting_price(Some(U128::from(ONE_NEAR)))
+        });
+        // Alice pays in NEAR
+        context.predecessor_account_id = "alice_near".parse::<AccountId>().unwrap();
+        (contract, context)
+    }
+
+    #[test]
+    fn try_admin_withdraw_near_keeps_deposits_and_pending() {
+        let (mut contract, mut context) = near_price_alice_at(200);
+        context.attached_deposit = NearToken::from_yoctonear(ONE_NEAR);
+        testing_env!(context.clone());
+        contract.nft_pack_buy(1, None, None);
+
+        context.predecessor_account_id = "admin_near".parse::<AccountId>().unwrap();
+        context.attached_deposit = NearToken::from_yoctonear(0);
+        context.account_balance = NearToken::from_yoctonear(100 * ONE_NEAR);
+        testing_env!(context.clone());
+        let storage_cost =
+            u128::from(env::storage_usage()) * env::storage_byte_cost().as_yoctonear();
+        // Alice storage deposit minus the storage of the pack and the pending NEAR payment
+        let reserved = 40_350_000_000_000_000_000_000 - MINT_STORAGE_COST + ONE_NEAR;
+        let withdrawable = 100 * ONE_NEAR - storage_cost - reserved;
+        assert_eq!(contract.get_withdrawable_near(), U128::from(withdrawable));
+
+        contract.admin_withdraw_near(U128::from(withdrawable));
+        assert_eq!(
+            get_logs(),
+            vec![format!(
+                r#"EVENT_JSON:{{"standard":"loterra_minter","version":"1.0.0","event":"admin_withdraw_near","data":[{{"receiver_id":"admin_near","amount":"{}"}}]}}"#,
+                withdrawable
+            )]
+        );
+
+        // The pending NEAR payment is released once the mint result is known
+        context.predecessor_account_id = context.current_account_id.clone();
+        set_promise_result(context, PromiseResult::Successful(vec![]));
+        contract.mint_result(
+            vec!["4999".to_string()],
+            "alice_near".parse::<AccountId>().unwrap(),
+            U128::from(MINT_STORAGE_COST),
+            U128::from(0),
+            U128::from(ONE_NEAR),
+            U128::from(0),
+            None,
+            None,
+            None,
+        );
+        // The refundable purchase is saved in the contract storage
+        let purchase_storage_cost = u128::from(env::storage_usage())
+            * env::storage_byte_cost().as_yoctonear()
+            - storage_cost;
+        assert_eq!(
+            contract.get_withdrawable_near(),
+            U128::from(withdrawable + ONE_NEAR - purchase_storage_cost)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "The amount is greater than the withdrawable NEAR balance 0")]
+    fn try_admin_withdraw_near_storage_deposits() {
+        let (mut contract, mut context) = near_price_alice_at(200);
+        // The whole balance is the storage deposit of Alice
+        context.predecessor_account_id = "admin_near".parse::<AccountId>().unwrap();
+        context.account_balance = NearToken::from_yoctonear(40_350_000_000_000_000_000_000);
+        testing_env!(context);
+        contract.admin_withdraw_near(U128::from(1));
+    }
+
+    #[test]
+    fn try_nft_pack_buy_with_near() {
+        let (mut contract, mut context) = near_price_alice_at(200);
+        // Overpay, 0.5 NEAR is refunded
+        context.attached_deposit = NearToken::from_yoctonear(2 * ONE_NEAR + ONE_NEAR / 2);
+        testing_env!(context.clone());
+        contract.nft_pack_buy(2, None, None);
+
+        assert_eq!(contract.get_minting_of(context.signer_account_id), 2);
+        assert_eq!(contract.get_pending_mints(), 2);
+        assert_eq!(
+            contract.get_near_minting_price(),
+            Some(U128::from(ONE_NEAR))
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Out of mint")]
+    fn try_nft_pack_buy_with_near_private_sale_limit() {
+        let (mut contract, mut context) = near_price_alice_at(150);
+        context.attached_deposit = NearToken::from_yoctonear(6 * ONE_NEAR);
+        testing_env!(context.clone());
+        contract.nft_pack_buy(3, None, None);
+        contract.nft_pack_buy(3, None, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Requires minimum deposit of 2000000000000000000000000 YoctoNear")]
+    fn try_nft_pack_buy_with_near_not_enough_deposit() {
+        let (mut contract, mut context) = near_price_alice_at(200);
+        context.attached_deposit = NearToken::from_yoctonear(ONE_NEAR);
+        testing_env!(context.clone());
+        contract.nft_pack_buy(2, None, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "NEAR payment is not enabled")]
+    fn try_nft_pack_buy_with_near_disabled() {
+        let (mut contract, mut context) = whitelisted_alice_at(150, 200);
+        context.predecessor_account_id = "alice_near".parse::<AccountId>().unwrap();
+        context.attached_deposit = NearToken::from_yoctonear(ONE_NEAR);
+        testing_env!(context.clone());
+        contract.nft_pack_buy(1, None, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Sale have not started yet")]
+    fn try_nft_pack_buy_with_near_sale_not_started() {
+        let (mut contract, mut context) = near_price_alice_at(150);
+        context.block_timestamp = 50;
+        context.attached_deposit = NearToken::from_yoctonear(ONE_NEAR);
+        testing_env!(context.clone());
+        contract.nft_pack_buy(1, None, None);
+    }
+
+    #[test]
+    fn try_mint_result_failed_refund_near() {
+        let (mut contract, mut context) = near_price_alice_at(200);
+        context.attached_deposit = NearToken::from_yoctonear(ONE_NEAR);
+        testing_env!(context.clone());
+        contract.nft_pack_buy(1, None, None);
+
+        set_promise_result(context.clone(), PromiseResult::Failed);
+        let refund = contract.mint_result(
+            vec!["4999".to_string()],
+            context.signer_account_id.clone(),
+            U128::from(MINT_STORAGE_COST),
+            U128::from(0),
+            U128::from(ONE_NEAR),
+            U128::from(0),
+            None,
+            None,
+            None,
+        );
+        // NEAR is transferred back, nothing to refund to a FT contract
+        assert_eq!(refund, U128::from(0));
+        assert_eq!(contract.get_minting_of(context.signer_account_id), 0);
+        assert_eq!(contract.get_nft_pack_supply(), 5000);
+    }
+
+    #[test]
+    fn try_init_pack_contract_account() {
+        let mut context = get_context(false);
+        context.current_account_id = "minter_near".parse::<AccountId>().unwrap();
+        testing_env!(context);
+        let contract = default_minter_init();
+
+        assert_eq!(
+            contract.nft_pack_contract,
+            "nft_pack.minter_near".parse::<AccountId>().unwrap()
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Spec is not NFT metadata")]
+    fn try_init_wrong_metadata_spec() {
+        let context = get_context(false);
+        testing_env!(context);
+        let mut metadata = default_pack_metadata();
+        metadata.spec = "nft-2.0.0".to_string();
+        Minter::new(
+            U128::from(100),
+            vec![],
+            100,
+            200,
+            5000,
+            metadata,
+            "nft_pack".to_string(),
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Symbol should be 1 to 16 characters")]
+    fn try_init_metadata_symbol_too_long() {
+        let context = get_context(false);
+        testing_env!(context);
+        let mut metadata = default_pack_metadata();
+        metadata.symbol = "LOTERRAPACKSYMBOL".to_string();
+        Minter::new(
+            U128::from(100),
+            vec![],
+            100,
+            200,
+            5000,
+            metadata,
+            "nft_pack".to_string(),
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Not a valid NFT pack account id")]
+    fn try_init_invalid_pack_account_prefix() {
+        let context = get_context(false);
+        testing_env!(context);
+        Minter::new(
+            U128::from(100),
+            vec![],
+            100,
+            200,
+            5000,
+            default_pack_metadata(),
+            "NFT Pack".to_string(),
+        );
+    }
+
+    #[test]
+    fn try_pack_contract_deployment_failed_and_redeploy() {
+        let mut context = get_context(false);
+        context.current_account_id = "minter_near".parse::<AccountId>().unwrap();
+        testing_env!(context.clone());
+        let mut contract = Minter::new(
+            U128::from(100),
+            vec![],
+            100,
+            200,
+            5000,
+            default_pack_metadata(),
+            "nft_pack".to_string(),
+        );
+        assert_eq!(contract.get_pack_contract().1, PackContractStatus::Pending);
+
+        set_promise_result(context.clone(), PromiseResult::Failed);
+        contract.on_pack_contract_deployed();
+        assert_eq!(contract.get_pack_contract().1, PackContractStatus::Failed);
+        assert_eq!(
+            get_logs(),
+            vec![
+                r#"EVENT_JSON:{"standard":"loterra_minter","version":"1.0.0","event":"pack_contract_deploy","data":[{"nft_pack_contract":"nft_pack.minter_near","status":"Failed"}]}"#
+            ]
+        );
+
+        testing_env!(context.clone());
+        contract.redeploy_pack_contract();
+        assert_eq!(contract.get_pack_contract().1, PackContractStatus::Pending);
+        assert_eq!(
+            get_logs(),
+            vec![
+                r#"EVENT_JSON:{"standard":"loterra_minter","version":"1.0.0","event":"pack_contract_redeploy","data":[{"nft_pack_contract":"nft_pack.minter_near","status":"Pending"}]}"#
+            ]
+        );
+
+        set_promise_result(context, PromiseResult::Successful(vec![]));
+        contract.on_pack_contract_deployed();
+        assert_eq!(
+            get_logs(),
+            vec![
+                r#"EVENT_JSON:{"standard":"loterra_minter","version":"1.0.0","event":"pack_contract_deploy","data":[{"nft_pack_contract":"nft_pack.minter_near","status":"Deployed"}]}"#
+            ]
+        );
+        assert_eq!(
+            contract.get_pack_contract(),
+            (
+                "nft_pack.minter_near".parse::<AccountId>().unwrap(),
+                PackContractStatus::Deployed
+            )
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "NFT pack contract deployment is not failed")]
+    fn try_redeploy_pack_contract_already_deployed() {
+        let context = get_context(false);
+        testing_env!(context);
+        let mut contract = default_minter_init();
+        contract.redeploy_pack_contract();
+    }
+
+    #[test]
+    #[should_panic(expected = "NFT pack contract is not deployed")]
+    fn try_mint_sending_ft_pack_contract_not_deployed() {
+        let (mut contract, context) = whitelisted_alice_at(150, 200);
+        contract.pack_contract_status = PackContractStatus::Pending;
+        contract.ft_on_transfer(
+            context.signer_account_id,
+            U128::from(100),
+            json!({
+                "mint_amount": 1
+            })
+            .to_string(),
+        );
+    }
+
+    fn admin_pause(contract: &mut Minter, context: &VMContext, scope: PauseScope, paused: bool) {
+        let mut admin_context = context.clone();
+        admin_context.signer_account_id = "admin_near".parse::<AccountId>().unwrap();
+        admin_context.predecessor_account_id = "admin_near".parse::<AccountId>().unwrap();
+        testing_env!(admin_context);
+        if paused {
+            contract.pause(scope);
+        } else {
+            contract.unpause(scope);
+        }
+        testing_env!(context.clone());
+    }
+
+    #[test]
+    #[should_panic(expected = "Minting is paused")]
+    fn try_nft_pack_buy_with_near_paused() {
+        let (mut contract, mut context) = near_price_alice_at(200);
+        context.attached_deposit = NearToken::from_yoctonear(ONE_NEAR);
+        admin_pause(&mut contract, &context, PauseScope::All, true);
+        contract.nft_pack_buy(1, None, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Public sale is paused")]
+    fn try_mint_sending_ft_public_sale_paused() {
+        let (mut contract, context) = whitelisted_alice_at(150, 200);
+        admin_pause(&mut contract, &context, PauseScope::PublicSale, true);
+        contract.ft_on_transfer(
+            context.signer_account_id,
+            U128::from(100),
+            json!({
+                "mint_amount": 1
+            })
+            .to_string(),
+        );
+    }
+
+    #[test]
+    fn try_mint_sending_ft_private_sale_public_sale_paused() {
+        let (mut contract, context) = whitelisted_alice_at(150, 150);
+        admin_pause(&mut contract, &context, PauseScope::PublicSale, true);
+        contract.ft_on_transfer(
+            context.signer_account_id.clone(),
+            U128::from(1000),
+            json!({
+                "mint_amount": 1
+            })
+            .to_string(),
+        );
+        assert_eq!(contract.get_minting_of(context.signer_account_id), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Payment token is paused")]
+    fn try_mint_sending_ft_payment_token_paused() {
+        let (mut contract, context) = whitelisted_alice_at(150, 200);
+        let usdc = "usdc_near".parse::<AccountId>().unwrap();
+        admin_pause(
+            &mut contract,
+            &context,
+            PauseScope::PaymentToken(usdc.clone()),
+            true,
+        );
+        assert!(contract.is_paused(PauseScope::PaymentToken(usdc)));
+        assert!(!contract.is_paused(PauseScope::All));
+        contract.ft_on_transfer(
+            context.signer_account_id,
+            U128::from(100),
+            json!({
+                "mint_amount": 1
+            })
+            .to_string(),
+        );
+    }
+
+    #[test]
+    fn try_pause_unpause_emit_events() {
+        let (mut contract, context) = whitelisted_alice_at(150, 200);
+        let mut admin_context = context.clone();
+        admin_context.signer_account_id = "admin_near".parse::<AccountId>().unwrap();
+        admin_context.predecessor_account_id = "admin_near".parse::<AccountId>().unwrap();
+        testing_env!(admin_context);
+        contract.pause(PauseScope::All);
+        contract.unpause(PauseScope::All);
+        contract.pause(PauseScope::PaymentToken(
+            "usdt_near".parse::<AccountId>().unwrap(),
+        ));
+
+        assert_eq!(
+            get_logs(),
+            vec![
+                r#"EVENT_JSON:{"standard":"loterra_minter","version":"1.0.0","event":"pause","data":[{"scope":"all"}]}"#,
+                r#"EVENT_JSON:{"standard":"loterra_minter","version":"1.0.0","event":"unpause","data":[{"scope":"all"}]}"#,
+                r#"EVENT_JSON:{"standard":"loterra_minter","version":"1.0.0","event":"pause","data":[{"scope":{"payment_token":"usdt_near"}}]}"#,
+            ]
+        );
+        testing_env!(context);
+        assert!(!contract.is_paused(PauseScope::All));
+    }
+
+    #[test]
+    fn try_storage_withdraw_while_paused() {
+        let (mut contract, mut context) = whitelisted_alice_at(150, 200);
+        admin_pause(&mut contract, &context, PauseScope::All, true);
+        context.predecessor_account_id = context.signer_account_id.clone();
+        context.attached_deposit = NearToken::from_yoctonear(ONE_YOCTO);
+        testing_env!(context);
+        contract.storage_withdraw(None);
+    }
+
+    #[test]
+    fn try_transfer_ownership() {
+        let mut context = get_context(false);
+        testing_env!(context.clone());
+        let mut contract = default_minter_init();
+        let bob = "bob_near".parse::<AccountId>().unwrap();
+        contract.propose_owner(bob.clone());
+        assert_eq!(
+            contract.get_owner(),
+            (
+                "admin_near".parse::<AccountId>().unwrap(),
+                Some(bob.clone())
+            )
+        );
+        assert_eq!(
+            get_logs()[2..],
+            vec![
+                r#"EVENT_JSON:{"standard":"loterra_minter","version":"1.0.0","event":"owner_propose","data":[{"owner_id":"admin_near","new_owner_id":"bob_near"}]}"#
+            ]
+        );
+
+        context.signer_account_id = bob.clone();
+        context.predecessor_account_id = bob.clone();
+        testing_env!(context);
+        contract.accept_ownership();
+        assert_eq!(contract.get_owner(), (bob.clone(), None));
+        contract.grant_role(bob.clone(), Role::Pauser);
+        assert_eq!(contract.get_roles(bob), vec![Role::Pauser]);
+        assert_eq!(
+            get_logs(),
+            vec![
+                r#"EVENT_JSON:{"standard":"loterra_minter","version":"1.0.0","event":"ownership_transfer","data":[{"owner_id":"admin_near","new_owner_id":"bob_near"}]}"#,
+                r#"EVENT_JSON:{"standard":"loterra_minter","version":"1.0.0","event":"role_grant","data":[{"account_id":"bob_near","role":"pauser"}]}"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn try_init_owner_is_predecessor() {
+        let mut context = get_context(false);
+        // The minter is deployed by a factory contract on behalf of the signer
+        context.predecessor_account_id = "factory_near".parse::<AccountId>().unwrap();
+        testing_env!(context);
+        let contract = default_minter_init();
+        assert_eq!(
+            contract.get_owner(),
+            ("factory_near".parse::<AccountId>().unwrap(), None)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the proposed owner can accept the ownership")]
+    fn try_accept_ownership_not_proposed() {
+        let mut context = get_context(false);
+        testing_env!(context.clone());
+        let mut contract = default_minter_init();
+        contract.propose_owner("bob_near".parse::<AccountId>().unwrap());
+
+        context.signer_account_id = "alice_near".parse::<AccountId>().unwrap();
+        context.predecessor_account_id = "alice_near".parse::<AccountId>().unwrap();
+        testing_env!(context);
+        contract.accept_ownership();
+    }
+
+    #[test]
+    #[should_panic(expected = "Owner's method")]
+    fn try_owner_method_called_through_contract() {
+        let mut context = get_context(false);
+        testing_env!(context.clone());
+        let mut contract = default_minter_init();
+        // Admin signed a transaction but another contract is calling the minter
+        context.predecessor_account_id = "dapp_near".parse::<AccountId>().unwrap();
+        testing_env!(context);
+        contract.set_near_minting_price(Some(U128::from(ONE_NEAR)));
+    }
+
+    #[test]
+    fn try_whitelist_manager_role() {
+        let mut context = get_context(false);
+        testing_env!(context.clone());
+        let mut contract = default_minter_init();
+        let bob = "bob_near".parse::<AccountId>().unwrap();
+        contract.grant_role(bob.clone(), Role::WhitelistManager);
+        contract.grant_role(bob.clone(), Role::Pauser);
+
+        context.signer_account_id = bob.clone();
+        context.predecessor_account_id = bob.clone();
+        testing_env!(context.clone());
+        contract.add_whitelist(
+            "alice_near".parse::<AccountId>().unwrap(),
+            100,
+            U128::from(1000),
+            5,
+        );
+        assert_eq!(contract.get_whitelist(0, 10).len(), 1);
+
+        context.signer_account_id = "admin_near".parse::<AccountId>().unwrap();
+        context.predecessor_account_id = "admin_near".parse::<AccountId>().unwrap();
+        testing_env!(context);
+        contract.revoke_role(bob.clone(), Role::WhitelistManager);
+        assert_eq!(contract.get_roles(bob), vec![Role::Pauser]);
+        assert_eq!(
+            get_logs(),
+            vec![
+                r#"EVENT_JSON:{"standard":"loterra_minter","version":"1.0.0","event":"role_revoke","data":[{"account_id":"bob_near","role":"whitelist_manager"}]}"#
+            ]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Requires WhitelistManager role")]
+    fn try_whitelist_with_other_role() {
+        let mut context = get_context(false);
+        testing_env!(context.clone());
+        let mut contract = default_minter_init();
+        let bob = "bob_near".parse::<AccountId>().unwrap();
+        contract.grant_role(bob.clone(), Role::Treasurer);
+
+        context.signer_account_id = bob.clone();
+        context.predecessor_account_id = bob;
+        testing_env!(context);
+        contract.add_whitelist(
+            "alice_near".parse::<AccountId>().unwrap(),
+            100,
+            U128::from(1000),
+            5,
+        );
+    }
+
+    fn migrated_v1_minter(mut context: VMContext) -> Minter {
+        context.current_account_id = "minter_near".parse::<AccountId>().unwrap();
+        testing_env!(context.clone());
+        let alice = "alice_near".parse::<AccountId>().unwrap();
+        let mut whitelist = UnorderedMap::new(b"s");
+        whitelist.insert(
+            &alice,
+            &WhitelistState {
+                minting_start: 100,
+                minting_price: U128::from(1000),
+                minting_limit: 5,
+            },
+        );
+        let mut minted = LookupMap::new(b"m");
+        minted.insert(&alice, &2);
+        let mut storage_deposits = LookupMap::new(b"d");
+        storage_deposits.insert(&alice, &U128::from(MINT_STORAGE_COST));
+        // State written by the v1 contract
+        env::state_write(&upgrade::MinterV1 {
+            whitelist,
+            minting_price: U128::from(100),
+            admin: "admin_near".parse::<AccountId>().unwrap(),
+            usdc_account_id: "usdc_near".parse::<AccountId>().unwrap(),
+            usdt_account_id: "usdt_near".parse::<AccountId>().unwrap(),
+            private_sale_start: 100,
+            public_sale_start: 200,
+            switch_off: false,
+            minted,
+            storage_deposits,
+            nft_pack_contract: "nft_pack9.minter_near".parse::<AccountId>().unwrap(),
+            nft_pack_supply: 4998,
+        });
+
+        context.predecessor_account_id = context.current_account_id.clone();
+        testing_env!(context);
+        Minter::migrate()
+    }
+
+    #[test]
+    fn try_migrate_v1_state() {
+        let contract = migrated_v1_minter(get_context(false));
+        let alice = "alice_near".parse::<AccountId>().unwrap();
+
+        assert_eq!(contract.get_state_version(), upgrade::STATE_VERSION);
+        assert_eq!(contract.get_whitelist(0, 10).len(), 1);
+        assert_eq!(contract.get_minting_of(alice.clone()), 2);
+        assert_eq!(
+            contract.storage_balance_of(alice).unwrap().total,
+            NearToken::from_yoctonear(MINT_STORAGE_COST)
+        );
+        assert_eq!(contract.get_nft_pack_supply(), 4998);
+        assert_eq!(
+            contract.get_pack_contract(),
+            (
+                "nft_pack9.minter_near".parse::<AccountId>().unwrap(),
+                PackContractStatus::Deployed
+            )
+        );
+        assert_eq!(contract.get_payment_tokens().len(), 2);
+        assert_eq!(
+            contract.get_owner(),
+            ("admin_near".parse::<AccountId>().unwrap(), None)
+        );
+    }
+
+    #[test]
+    fn try_set_migrated_storage_deposits() {
+        let mut context = get_context(false);
+        context.account_balance = NearToken::from_yoctonear(100 * ONE_NEAR);
+        let mut contract = migrated_v1_minter(context.clone());
+        // The whole balance is reserved until the deposits are summed
+        assert_eq!(contract.total_storage_deposits, 100 * ONE_NEAR);
+
+        context.current_account_id = "minter_near".parse::<AccountId>().unwrap();
+        testing_env!(context);
+        contract.set_migrated_storage_deposits(U128::from(MINT_STORAGE_COST));
+        assert_eq!(contract.total_storage_deposits, MINT_STORAGE_COST);
+        assert_eq!(
+            get_logs(),
+            vec![
+                r#"EVENT_JSON:{"standard":"loterra_minter","version":"1.0.0","event":"migrated_deposits_set","data":[{"total_storage_deposits":"8000000000000000000000"}]}"#
+            ]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "No migrated storage deposits to set")]
+    fn try_set_migrated_storage_deposits_twice() {
+        let mut context = get_context(false);
+        context.account_balance = NearToken::from_yoctonear(100 * ONE_NEAR);
+        let mut contract = migrated_v1_minter(context.clone());
+        context.current_account_id = "minter_near".parse::<AccountId>().unwrap();
+        testing_env!(context);
+        contract.set_migrated_storage_deposits(U128::from(MINT_STORAGE_COST));
+        contract.set_migrated_storage_deposits(U128::from(0));
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "The storage deposits can't exceed the 100000000000000000000000000 YoctoNear reserved"
+    )]
+    fn try_set_migrated_storage_deposits_above_balance() {
+        let mut context = get_context(false);
+        context.account_balance = NearToken::from_yoctonear(100 * ONE_NEAR);
+        let mut contract = migrated_v1_minter(context.clone());
+        context.current_account_id = "minter_near".parse::<AccountId>().unwrap();
+        testing_env!(context);
+        contract.set_migrated_storage_deposits(U128::from(101 * ONE_NEAR));
+    }
+
+    #[test]
+    #[should_panic(expected = "No migrated storage deposits to set")]
+    fn try_set_migrated_storage_deposits_not_migrated() {
+        let context = get_context(false);
+        testing_env!(context);
+        let mut contract = default_minter_init();
+        contract.set_migrated_storage_deposits(U128::from(0));
+    }
+
+    #[test]
+    fn try_migrate_current_state() {
+        let mut context = get_context(false);
+        testing_env!(context.clone());
+        let mut contract = default_minter_init();
+        contract.set_near_minting_price(Some(U128::from(ONE_NEAR)));
+        env::state_write(&contract);
+
+        context.predecessor_account_id = context.current_account_id.clone();
+        testing_env!(context);
+        let contract = Minter::migrate();
+        assert_eq!(contract.get_state_version(), upgrade::STATE_VERSION);
+        assert_eq!(
+            contract.get_near_minting_price(),
+            Some(U128::from(ONE_NEAR))
+        );
+    }
+
+    #[test]
+    fn try_upgrade_by_upgrader() {
+        let mut context = get_context(false);
+        testing_env!(context.clone());
+        let mut contract = default_minter_init();
+        let bob = "bob_near".parse::<AccountId>().unwrap();
+        contract.grant_role(bob.clone(), Role::Upgrader);
+
+        context.predecessor_account_id = bob;
+        context.input = vec![0, 97, 115, 109];
+        testing_env!(context);
+        contract.upgrade();
+    }
+
+    #[test]
+    #[should_panic(expected = "Requires Upgrader role")]
+    fn try_upgrade_not_authorized() {
+        let mut context = get_context(false);
+        testing_env!(context.clone());
+        let contract = default_minter_init();
+
+        context.predecessor_account_id = "bob_near".parse::<AccountId>().unwrap();
+        context.input = vec![0, 97, 115, 109];
+        testing_env!(context);
+        contract.upgrade();
+    }
+
+    #[test]
+    fn try_whitelist_events() {
+        let context = get_context(false);
+        testing_env!(context);
+        let mut contract = default_minter_init();
+        let alice = "alice_near".parse::<AccountId>().unwrap();
+        contract.add_whitelist(alice.clone(), 100, U128::from(1000), 5);
+        contract.delete_whitelist(alice);
+
+        assert_eq!(
+            get_logs()[2..],
+            vec![
+                r#"EVENT_JSON:{"standard":"loterra_minter","version":"1.0.0","event":"whitelist_add","data":[{"account_id":"alice_near","minting_start":100,"minting_price":"1000","minting_limit":5}]}"#,
+                r#"EVENT_JSON:{"standard":"loterra_minter","version":"1.0.0","event":"whitelist_remove","data":[{"account_id":"alice_near"}]}"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn try_init_phase_change_event() {
+        let context = get_context(false);
+        testing_env!(context);
+        default_minter_init();
+
+        assert_eq!(
+            get_logs()[1],
+            r#"EVENT_JSON:{"standard":"loterra_minter","version":"1.0.0","event":"phase_change","data":[{"private_sale_start":100,"public_sale_start":200}]}"#
+        );
+    }
+
+    #[test]
+    fn try_get_sale_phase() {
+        let mut context = get_context(false);
+        testing_env!(context.clone());
+        let mut contract = default_minter_init();
+        contract.set_public_sale_end(Some(300));
+        assert_eq!(contract.get_sale_phase(), SalePhase::NotStarted);
+
+        for (block_timestamp, phase) in [
+            (150, SalePhase::Private),
+            (250, SalePhase::Public),
+            (300, SalePhase::Ended),
+        ] {
+            context.block_timestamp = block_timestamp;
+            testing_env!(context.clone());
+            assert_eq!(contract.get_sale_phase(), phase);
+        }
+
+        context.block_timestamp = 250;
+        testing_env!(context.clone());
+        admin_pause(&mut contract, &context, PauseScope::PublicSale, true);
+        assert_eq!(contract.get_sale_phase(), SalePhase::Paused);
+        admin_pause(&mut contract, &context, PauseScope::PublicSale, false);
+        contract.nft_pack_supply = 0;
+        assert_eq!(contract.get_sale_phase(), SalePhase::SoldOut);
+    }
+
+    #[test]
+    fn try_reschedule_sale() {
+        let mut context = get_context(false);
+        context.block_timestamp = 50;
+        testing_env!(context.clone());
+        let mut contract = default_minter_init();
+        contract.reschedule_public_sale(400);
+        contract.reschedule_private_sale(60);
+        assert_eq!(contract.get_sale_schedule(), (60, 400, None));
+        assert_eq!(
+            get_logs()[2..],
+            vec![
+                r#"EVENT_JSON:{"standard":"loterra_minter","version":"1.0.0","event":"phase_change","data":[{"private_sale_start":100,"public_sale_start":400}]}"#,
+                r#"EVENT_JSON:{"standard":"loterra_minter","version":"1.0.0","event":"phase_change","data":[{"private_sale_start":60,"public_sale_start":400}]}"#
+            ]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "The private sale already started")]
+    fn try_reschedule_private_sale_started() {
+        let mut context = get_context(false);
+        testing_env!(context.clone());
+        let mut contract = default_minter_init();
+        context.block_timestamp = 150;
+        testing_env!(context);
+        contract.reschedule_private_sale(160);
+    }
+
+    #[test]
+    #[should_panic(expected = "Sale has ended")]
+    fn try_mint_sending_ft_sale_ended() {
+        let (mut contract, mut context) = whitelisted_alice_at(150, 100);
+        context.predecessor_account_id = "admin_near".parse::<AccountId>().unwrap();
+        testing_env!(context.clone());
+        contract.set_public_sale_end(Some(300));
+        context.predecessor_account_id = "usdc_near".parse::<AccountId>().unwrap();
+        context.block_timestamp = 300;
+        testing_env!(context.clone());
+        contract.ft_on_transfer(
+            context.signer_account_id,
+            U128::from(100),
+            json!({
+                "mint_amount": 1
+            })
+            .to_string(),
+        );
+    }
+
+    fn payee(account_id: &str, share_bps: u16) -> Payee {
+        Payee {
+            account_id: account_id.parse::<AccountId>().unwrap(),
+            share_bps,
+        }
+    }
+
+    /// Alice buys 3 NFT packs at the public price and the mint succeeds
+    fn usdc_purchase_settled(contract: &mut Minter, context: &VMContext) {
+        contract.ft_on_transfer(
+            context.signer_account_id.clone(),
+            U128::from(300),
+            json!({
+                "mint_amount": 3
+            })
+            .to_string(),
+        );
+        set_promise_result(context.clone(), PromiseResult::Successful(vec![]));
+        contract.mint_result(
+            vec!["4999".to_string(), "4998".to_string(), "4997".to_string()],
+            context.signer_account_id.clone(),
+            U128::from(3 * MINT_STORAGE_COST),
+            U128::from(0),
+            U128::from(300),
+            U128::from(0),
+            None,
+            Some("usdc_near".parse::<AccountId>().unwrap()),
+            None,
+        );
+    }
+
+    #[test]
+    fn try_distribute_and_claim_payout() {
+        let (mut contract, mut context) = whitelisted_alice_at(150, 200);
+        let usdc = "usdc_near".parse::<AccountId>().unwrap();
+        let dao = "dao_near".parse::<AccountId>().unwrap();
+        usdc_purchase_settled(&mut contract, &context);
+        assert_eq!(contract.get_proceeds(usdc.clone()), U128::from(300));
+
+        context.predecessor_account_id = "admin_near".parse::<AccountId>().unwrap();
+        testing_env!(context.clone());
+        contract.set_payees(vec![
+            payee("team_near", 6000),
+            payee("dao_near", 3333),
+            payee("artist_near", 667),
+        ]);
+        contract.distribute(usdc.clone());
+        assert_eq!(
+            get_logs()[1],
+            r#"EVENT_JSON:{"standard":"loterra_minter","version":"1.0.0","event":"distribute","data":[{"account_id":"team_near","token_account_id":"usdc_near","amount":"180"},{"account_id":"dao_near","token_account_id":"usdc_near","amount":"99"},{"account_id":"artist_near","token_account_id":"usdc_near","amount":"20"}]}"#
+        );
+        // The rounding remainder stays undistributed
+        assert_eq!(contract.get_proceeds(usdc.clone()), U128::from(1));
+
+        context.predecessor_account_id = dao.clone();
+        testing_env!(context.clone());
+        contract.claim_payout(usdc.clone());
+        assert_eq!(
+            contract.get_payout(dao.clone(), usdc.clone()),
+            Payout {
+                claimable: U128::from(0),
+                paid: U128::from(99),
+            }
+        );
+
+        // The payout is claimable again when the transfer failed
+        context.predecessor_account_id = context.current_account_id.clone();
+        set_promise_result(context.clone(), PromiseResult::Failed);
+        contract.on_payout_claimed(dao.clone(), usdc.clone(), U128::from(99));
+        assert_eq!(
+            contract.get_payout(dao, usdc),
+            Payout {
+                claimable: U128::from(99),
+                paid: U128::from(0),
+            }
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Payee shares should sum to 10000 basis points")]
+    fn try_set_payees_wrong_total_share() {
+        let context = get_context(false);
+        testing_env!(context);
+        let mut contract = default_minter_init();
+        contract.set_payees(vec![payee("team_near", 6000), payee("dao_near", 3000)]);
+    }
+
+    #[test]
+    fn try_storage_events() {
+        let mut context = get_context(false);
+        context.signer_account_id = "alice_near".parse::<AccountId>().unwrap();
+        context.predecessor_account_id = "alice_near".parse::<AccountId>().unwrap();
+        context.attached_deposit = NearToken::from_yoctonear(MINT_STORAGE_COST);
+        testing_env!(context.clone());
+        let mut contract = default_minter_init();
+        contract.storage_deposit(None, None);
+        contract.storage_deposit(None, None);
+        assert_eq!(
+            get_logs().last().unwrap(),
+            r#"EVENT_JSON:{"standard":"loterra_minter","version":"1.0.0","event":"storage_deposit","data":[{"account_id":"alice_near","amount":"8000000000000000000000","balance":"16000000000000000000000"}]}"#
+        );
+        context.attached_deposit = NearToken::from_yoctonear(ONE_YOCTO);
+        testing_env!(context);
+        contract.storage_withdraw(None);
+
+        assert_eq!(
+            get_logs(),
+            vec![
+                r#"EVENT_JSON:{"standard":"loterra_minter","version":"1.0.0","event":"storage_withdraw","data":[{"account_id":"alice_near","amount":"16000000000000000000000","balance":"0"}]}"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn try_purchase_and_mint_events() {
+        let (mut contract, context) = whitelisted_alice_at(150, 200);
+        contract.ft_on_transfer(
+            context.signer_account_id.clone(),
+            U128::from(200),
+            json!({
+                "mint_amount": 2
+            })
+            .to_string(),
+        );
+        assert_eq!(
+            get_logs()[get_logs().len() - 2..],
+            vec![
+                r#"EVENT_JSON:{"standard":"loterra_minter","version":"1.0.0","event":"supply_change","data":[{"supply":4998,"pending":2}]}"#,
+                r#"EVENT_JSON:{"standard":"loterra_minter","version":"1.0.0","event":"purchase_started","data":[{"buyer_id":"alice_near","token_ids":["4999","4998"],"payment_token":"usdc_near","amount":"200"}]}"#,
+            ]
+        );
+
+        set_promise_result(context.clone(), PromiseResult::Failed);
+        contract.mint_result(
+            vec!["4999".to_string(), "4998".to_string()],
+            context.signer_account_id,
+            U128::from(2 * MINT_STORAGE_COST),
+            U128::from(0),
+            U128::from(200),
+            U128::from(0),
+            None,
+            Some("usdc_near".parse::<AccountId>().unwrap()),
+            None,
+        );
+        assert_eq!(
+            get_logs(),
+            vec![
+                r#"EVENT_JSON:{"standard":"loterra_minter","version":"1.0.0","event":"mint_failed","data":[{"buyer_id":"alice_near","token_ids":["4999","4998"],"payment_token":"usdc_near","amount":"200"}]}"#,
+                r#"EVENT_JSON:{"standard":"loterra_minter","version":"1.0.0","event":"supply_change","data":[{"supply":5000,"pending":0}]}"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn try_mint_succeeded_event_paid_in_near() {
+        let (mut contract, mut context) = near_price_alice_at(200);
+        context.attached_deposit = NearToken::from_yoctonear(ONE_NEAR);
+        testing_env!(context.clone());
+        contract.nft_pack_buy(1, None, None);
+
+        set_promise_result(context.clone(), PromiseResult::Successful(vec![]));
+        contract.mint_result(
+            vec!["4999".to_string()],
+            context.signer_account_id,
+            U128::from(MINT_STORAGE_COST),
+            U128::from(0),
+            U128::from(ONE_NEAR),
+            U128::from(0),
+            None,
+            None,
+            None,
+        );
+        assert_eq!(
+            get_logs(),
+            vec![
+                r#"EVENT_JSON:{"standard":"loterra_minter","version":"1.0.0","event":"mint_succeeded","data":[{"buyer_id":"alice_near","token_ids":["4999"],"payment_token":null,"amount":"1000000000000000000000000"}]}"#,
+                r#"EVENT_JSON:{"standard":"loterra_minter","version":"1.0.0","event":"supply_change","data":[{"supply":4999,"pending":0}]}"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn try_admin_collect_event() {
+        let (mut contract, mut context) = whitelisted_alice_at(150, 200);
+        let usdc = "usdc_near".parse::<AccountId>().unwrap();
+        usdc_purchase_settled(&mut contract, &context);
+        context.predecessor_account_id = "admin_near".parse::<AccountId>().unwrap();
+        testing_env!(context);
+        contract.admin_collect(usdc.clone(), U128::from(300));
+        assert_eq!(
+            get_logs().last().unwrap(),
+            r#"EVENT_JSON:{"standard":"loterra_minter","version":"1.0.0","event":"admin_collect","data":[{"token_account_id":"usdc_near","receiver_id":"admin_near","amount":"300"}]}"#
+        );
+        assert_eq!(
+            contract.get_payment_totals(usdc),
+            PaymentTotals {
+                received: U128::from(300),
+                refunded: U128::from(0),
+                withdrawn: U128::from(300),
+            }
+        );
+    }
+
+    #[test]
+    fn try_admin_collect_failed_rollback() {
+        let (mut contract, mut context) = whitelisted_alice_at(150, 200);
+        let usdc = "usdc_near".parse::<AccountId>().unwrap();
+        usdc_purchase_settled(&mut contract, &context);
+        context.predecessor_account_id = "admin_near".parse::<AccountId>().unwrap();
+        testing_env!(context.clone());
+        contract.admin_collect(usdc.clone(), U128::from(200));
+        assert_eq!(contract.get_proceeds(usdc.clone()), U128::from(100));
+
+        context.predecessor_account_id = context.current_account_id.clone();
+        set_promise_result(context, PromiseResult::Failed);
+        contract.on_admin_collected(usdc.clone(), U128::from(200));
+        assert_eq!(contract.get_proceeds(usdc.clone()), U128::from(300));
+        assert_eq!(contract.get_payment_totals(usdc).withdrawn, U128::from(0));
+    }
+
+    #[test]
+    #[should_panic(expected = "The amount is greater than the collectable balance 300")]
+    fn try_admin_collect_above_balance() {
+        let (mut contract, mut context) = whitelisted_alice_at(150, 200);
+        usdc_purchase_settled(&mut contract, &context);
+        context.predecessor_account_id = "admin_near".parse::<AccountId>().unwrap();
+        testing_env!(context);
+        contract.admin_collect("usdc_near".parse::<AccountId>().unwrap(), U128::from(301));
+    }
+
+    #[test]
+    fn try_payment_totals_refunded() {
+        let (mut contract, context) = whitelisted_alice_at(150, 200);
+        let usdc = "usdc_near".parse::<AccountId>().unwrap();
+        contract.ft_on_transfer(
+            context.signer_account_id.clone(),
+            U128::from(300),
+            json!({
+                "mint_amount": 3
+            })
+            .to_string(),
+        );
+        set_promise_result(context.clone(), PromiseResult::Failed);
+        contract.mint_result(
+            vec!["4999".to_string(), "4998".to_string(), "4997".to_string()],
+            context.signer_account_id,
+            U128::from(3 * MINT_STORAGE_COST),
+            U128::from(0),
+            U128::from(300),
+            U128::from(0),
+            None,
+            Some(usdc.clone()),
+            None,
+        );
+        assert_eq!(
+            contract.get_payment_totals(usdc.clone()),
+            PaymentTotals {
+                received: U128::from(300),
+                refunded: U128::from(300),
+                withdrawn: U128::from(0),
+            }
+        );
+        assert_eq!(contract.get_proceeds(usdc), U128::from(0));
+    }
+
     #[test]
-    fn try_whitelist() {
+    fn try_storage_deposit_registration_only() {
         let mut context = get_context(false);
-        context.signer_account_id = AccountId::new_unchecked("admin_near".to_string());
+        context.predecessor_account_id = "alice_near".parse::<AccountId>().unwrap();
+        context.attached_deposit = NearToken::from_yoctonear(2 * MINT_STORAGE_COST);
+        testing_env!(context.clone());
+        let mut contract = default_minter_init();
+        // Register with the minimum, the rest is refunded
+        let balance = contract.storage_deposit(None, Some(true));
+        assert_eq!(balance.total, NearToken::from_yoctonear(MINT_STORAGE_COST));
+        // Already registered, the full deposit is refunded
+        let balance = contract.storage_deposit(None, Some(true));
+        assert_eq!(balance.total, NearToken::from_yoctonear(MINT_STORAGE_COST));
+        // Registered account can deposit any amount
+        context.attached_deposit = NearToken::from_yoctonear(ONE_YOCTO);
         testing_env!(context);
+        let balance = contract.storage_deposit(None, None);
+        assert_eq!(
+            balance.available,
+            NearToken::from_yoctonear(MINT_STORAGE_COST + ONE_YOCTO)
+        );
+    }
 
+    #[test]
+    fn try_storage_withdraw_partial_and_unregister() {
+        let mut context = get_context(false);
+        let alice = "alice_near".parse::<AccountId>().unwrap();
+        context.predecessor_account_id = alice.clone();
+        context.attached_deposit = NearToken::from_yoctonear(3 * MINT_STORAGE_COST);
+        testing_env!(context.clone());
         let mut contract = default_minter_init();
-        contract.add_whitelist(
-            AccountId::new_unchecked("alice_near".to_string()),
-            env::block_timestamp().checked_add(100).unwrap(),
-            U128::from(1000),
-            5,
-        );
+        contract.storage_deposit(None, None);
 
+        context.attached_deposit = NearToken::from_yoctonear(ONE_YOCTO);
+        testing_env!(context);
+        let balance = contract.storage_withdraw(Some(NearToken::from_yoctonear(MINT_STORAGE_COST)));
         assert_eq!(
-            contract.get_whitelist(0, 10),
-            vec![(
-                AccountId::new_unchecked("alice_near".to_string()),
-                WhitelistState {
-                    minting_start: 100,
-                    minting_price: U128::from(1000),
-                    minting_limit: 5
-                }
-            )]
+            balance.total,
+            NearToken::from_yoctonear(2 * MINT_STORAGE_COST)
+        );
+        // The account stays registered with an empty balance
+        contract.storage_withdraw(None);
+        assert_eq!(
+            contract.storage_balance_of(alice.clone()).unwrap().total,
+            NearToken::from_yoctonear(0)
         );
+
+        assert!(contract.storage_unregister(None));
+        assert!(contract.storage_balance_of(alice).is_none());
+        assert!(!contract.storage_unregister(Some(true)));
     }
 
     #[test]
-    #[should_panic]
-    fn try_whitelist_not_authorized() {
+    #[should_panic(expected = "The amount is greater than the available balance")]
+    fn try_storage_withdraw_more_than_balance() {
         let mut context = get_context(false);
+        context.predecessor_account_id = "alice_near".parse::<AccountId>().unwrap();
+        context.attached_deposit = NearToken::from_yoctonear(MINT_STORAGE_COST);
+        testing_env!(context.clone());
         let mut contract = default_minter_init();
-        context.signer_account_id = AccountId::new_unchecked("alice_near".to_string());
-        testing_env!(context);
+        contract.storage_deposit(None, None);
 
-        contract.add_whitelist(
-            AccountId::new_unchecked("alice_near".to_string()),
-            env::block_timestamp().checked_add(100).unwrap(),
-            U128::from(10),
-            5,
-        )
+        context.attached_deposit = NearToken::from_yoctonear(ONE_YOCTO);
+        testing_env!(context);
+        contract.storage_withdraw(Some(NearToken::from_yoctonear(2 * MINT_STORAGE_COST)));
     }
 
     #[test]
-    fn admin_try_delete() {
-        let mut context = get_context(false);
-        context.signer_account_id = AccountId::new_unchecked("admin_near".to_string());
+    fn try_storage_balance_bounds() {
+        let context = get_context(false);
         testing_env!(context);
+        let contract = default_minter_init();
+        let bounds = contract.storage_balance_bounds();
+        assert_eq!(bounds.min, NearToken::from_yoctonear(MINT_STORAGE_COST));
+        assert!(bounds.max.is_none());
+    }
+
+    /// Alice buys 3 NFT packs in the public sale with a soft cap of 5 NFT packs
+    fn soft_cap_purchase_settled() -> (Minter, VMContext) {
+        let (mut contract, mut context) = whitelisted_alice_at(150, 200);
+        context.predecessor_account_id = "admin_near".parse::<AccountId>().unwrap();
+        testing_env!(context.clone());
+        contract.set_public_sale_end(Some(300));
+        contract.set_soft_cap(Some(SoftCap::Packs(5)));
+        context.predecessor_account_id = "usdc_near".parse::<AccountId>().unwrap();
+        testing_env!(context.clone());
+        usdc_purchase_settled(&mut contract, &context);
+        (contract, context)
+    }
+
+    #[test]
+    fn try_set_soft_cap_event() {
+        let context = get_context(false);
+        testing_env!(context.clone());
         let mut contract = default_minter_init();
-        // Admin add alice_near account
-        contract.add_whitelist(
-            AccountId::new_unchecked("alice_near".to_string()),
-            env::block_timestamp().checked_add(100).unwrap(),
-            U128::from(1000),
-            5,
+        contract.set_public_sale_end(Some(300));
+        testing_env!(context);
+        contract.set_soft_cap(Some(SoftCap::Proceeds(U128::from(1000))));
+        assert_eq!(
+            get_logs(),
+            vec![
+                r#"EVENT_JSON:{"standard":"loterra_minter","version":"1.0.0","event":"soft_cap_set","data":[{"soft_cap":{"proceeds":"1000"}}]}"#
+            ]
         );
-        // Admin delete alice_near account
-        contract.delete_whitelist(AccountId::new_unchecked("alice_near".to_string()));
+    }
 
-        assert_eq!(contract.get_whitelist(0, 10), vec![]);
+    #[test]
+    #[should_panic(expected = "The NEAR payment is not counted in the proceeds soft cap")]
+    fn try_set_proceeds_soft_cap_with_near_payment() {
+        let context = get_context(false);
+        testing_env!(context);
+        let mut contract = default_minter_init();
+        contract.set_public_sale_end(Some(300));
+        contract.set_near_minting_price(Some(U128::from(ONE_NEAR)));
+        contract.set_soft_cap(Some(SoftCap::Proceeds(U128::from(1000))));
     }
 
     #[test]
-    #[should_panic]
-    fn unauthorized_account_try_to_delete_accounts() {
-        let mut context = get_context(false);
-        context.signer_account_id = AccountId::new_unchecked("admin_near".to_string());
+    #[should_panic(expected = "The NEAR payment is not counted in the proceeds soft cap")]
+    fn try_set_near_minting_price_with_proceeds_soft_cap() {
+        let context = get_context(false);
         testing_env!(context);
         let mut contract = default_minter_init();
-        // Admin add alice_near account
-        contract.add_whitelist(
-            AccountId::new_unchecked("alice_near".to_string()),
-            env::block_timestamp().checked_add(100).unwrap(),
-            U128::from(1000),
-            5,
+        contract.set_public_sale_end(Some(300));
+        contract.set_soft_cap(Some(SoftCap::Proceeds(U128::from(1000))));
+        contract.set_near_minting_price(Some(U128::from(ONE_NEAR)));
+    }
+
+    #[test]
+    #[should_panic(expected = "Set the sale end before the soft cap")]
+    fn try_set_soft_cap_without_sale_end() {
+        let context = get_context(false);
+        testing_env!(context);
+        let mut contract = default_minter_init();
+        contract.set_soft_cap(Some(SoftCap::Packs(5)));
+    }
+
+    #[test]
+    #[should_panic(expected = "The sale end is required by the soft cap")]
+    fn try_remove_sale_end_with_soft_cap() {
+        let (mut contract, mut context) = soft_cap_purchase_settled();
+        context.predecessor_account_id = "admin_near".parse::<AccountId>().unwrap();
+        testing_env!(context);
+        contract.set_public_sale_end(None);
+    }
+
+    #[test]
+    fn try_soft_cap_progress() {
+        let (contract, _) = soft_cap_purchase_settled();
+        assert_eq!(
+            contract.get_soft_cap(),
+            SoftCapStatus {
+                soft_cap: Some(SoftCap::Packs(5)),
+                packs_sold: 3,
+                sale_proceeds: U128::from(300),
+                reached: false,
+                refunds_open: false,
+            }
         );
-        let mut context = get_context(false);
-        context.signer_account_id = AccountId::new_unchecked("bob_near".to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "The proceeds are locked until the soft cap is reached")]
+    fn try_admin_collect_below_soft_cap() {
+        let (mut contract, mut context) = soft_cap_purchase_settled();
+        context.predecessor_account_id = "admin_near".parse::<AccountId>().unwrap();
         testing_env!(context);
-        // Admin delete alice_near account
-        contract.delete_whitelist(AccountId::new_unchecked("alice_near".to_string()));
+        contract.admin_collect("usdc_near".parse::<AccountId>().unwrap(), U128::from(300));
     }
 
     #[test]
-    fn try_storage_deposit() {
-        let mut context = get_context(false);
-        context.signer_account_id = AccountId::new_unchecked("alice_near".to_string());
-        // Attach minimum deposit amount to the sender
-        context.attached_deposit = U128::from(5_870_000_000_000_000_000_000).0;
+    fn try_cancel_sale_and_claim_refund() {
+        let (mut contract, mut context) = whitelisted_alice_at(150, 200);
+        let alice = context.signer_account_id.clone();
+        let usdc = "usdc_near".parse::<AccountId>().unwrap();
+        usdc_purchase_settled(&mut contract, &context);
+        assert_eq!(
+            contract.get_refund(alice.clone(), Some(usdc.clone())),
+            U128::from(0)
+        );
+
+        context.predecessor_account_id = "admin_near".parse::<AccountId>().unwrap();
         testing_env!(context.clone());
-        let mut contract = default_minter_init();
-        // Deposit to the current signer account
-        contract.storage_deposit(None);
-        // Verify storage deposit
+        contract.cancel_sale();
+        assert_eq!(
+            get_logs(),
+            vec![
+                r#"EVENT_JSON:{"standard":"loterra_minter","version":"1.0.0","event":"sale_cancel","data":[{"packs_sold":3,"sale_proceeds":"300"}]}"#
+            ]
+        );
+        assert_eq!(contract.get_sale_phase(), SalePhase::Cancelled);
         assert_eq!(
-            contract.get_storage_balance_of(context.clone().signer_account_id),
-            U128::from(5_870_000_000_000_000_000_000)
+            contract.get_refund(alice.clone(), Some(usdc.clone())),
+            U128::from(300)
         );
 
-        //let mut context = get_context(false);
-        // Attach minimum deposit amount to the sender
-        context.attached_deposit = U128::from(10_000_000_000_000_000_000_000).0;
+        context.predecessor_account_id = alice.clone();
         testing_env!(context.clone());
-        let mut contract = default_minter_init();
-        let bob_account = AccountId::new_unchecked("bob_near".to_string());
-        // Deposit to the desired account
-        contract.storage_deposit(Some(bob_account.clone()));
-        // Verify Bob storage deposit
+        contract.claim_refund(Some(usdc.clone()), None);
         assert_eq!(
-            contract.get_storage_balance_of(bob_account),
-            U128::from(10_000_000_000_000_000_000_000)
+            get_logs(),
+            vec![
+                r#"EVENT_JSON:{"standard":"loterra_minter","version":"1.0.0","event":"refund_claim","data":[{"account_id":"alice_near","payment_token":"usdc_near","amount":"300"}]}"#
+            ]
+        );
+        assert_eq!(
+            contract.get_refund(alice.clone(), Some(usdc.clone())),
+            U128::from(0)
+        );
+        assert_eq!(
+            contract.get_payment_totals(usdc.clone()).refunded,
+            U128::from(300)
+        );
+
+        // The refund is claimable again when the transfer failed
+        context.predecessor_account_id = context.current_account_id.clone();
+        set_promise_result(context, PromiseResult::Failed);
+        contract.on_refund_claimed(
+            alice.clone(),
+            Some(usdc.clone()),
+            Purchase {
+                packs: 3,
+                paid: U128::from(300),
+            },
+            None,
         );
-        // Verify Alice account storage deposit remain the same
         assert_eq!(
-            contract.get_storage_balance_of(context.clone().signer_account_id),
-            U128::from(5_870_000_000_000_000_000_000)
+            contract.get_refund(alice, Some(usdc.clone())),
+            U128::from(300)
         );
+        assert_eq!(contract.get_payment_totals(usdc).refunded, U128::from(0));
     }
 
     #[test]
-    #[should_panic]
-    fn try_storage_deposit_less_than_authorized() {
-        let mut context = get_context(false);
-        // Attach less than minimum deposit amount to the sender
-        // min 5_870_000_000_000_000_000_000 attached 4_870_000_000_000_000_000_000
-        context.attached_deposit = U128::from(4_870_000_000_000_000_000_000).0;
+    #[should_panic(expected = "The sale is cancelled")]
+    fn try_mint_sending_ft_sale_cancelled() {
+        let (mut contract, mut context) = whitelisted_alice_at(150, 200);
+        context.predecessor_account_id = "admin_near".parse::<AccountId>().unwrap();
         testing_env!(context.clone());
-        let mut contract = default_minter_init();
-        // Deposit to the current signer account
-        contract.storage_deposit(None);
+        contract.cancel_sale();
+        context.predecessor_account_id = "usdc_near".parse::<AccountId>().unwrap();
+        testing_env!(context.clone());
+        usdc_purchase_settled(&mut contract, &context);
     }
 
     #[test]
-    fn try_storage_deposit_withdraw() {
-        let mut context = get_context(false);
-        context.signer_account_id = AccountId::new_unchecked("alice_near".to_string());
-        // Attach minimum deposit amount to the sender
-        context.attached_deposit = U128::from(5_870_000_000_000_000_000_000).0;
+    #[should_panic(expected = "Proceeds were already withdrawn")]
+    fn try_cancel_sale_after_admin_collect() {
+        let (mut contract, mut context) = whitelisted_alice_at(150, 200);
+        usdc_purchase_settled(&mut contract, &context);
+        context.predecessor_account_id = "admin_near".parse::<AccountId>().unwrap();
+        testing_env!(context);
+        contract.admin_collect("usdc_near".parse::<AccountId>().unwrap(), U128::from(100));
+        contract.cancel_sale();
+    }
+
+    #[test]
+    #[should_panic(expected = "Refunds are not open")]
+    fn try_claim_refund_before_sale_end() {
+        let (mut contract, mut context) = soft_cap_purchase_settled();
+        context.predecessor_account_id = context.signer_account_id.clone();
+        testing_env!(context);
+        contract.claim_refund(Some("usdc_near".parse::<AccountId>().unwrap()), None);
+    }
+
+    #[test]
+    fn try_claim_refund_soft_cap_missed_with_pack_burn() {
+        let (mut contract, mut context) = soft_cap_purchase_settled();
+        let alice = context.signer_account_id.clone();
+        let usdc = "usdc_near".parse::<AccountId>().unwrap();
+        context.predecessor_account_id = "admin_near".parse::<AccountId>().unwrap();
         testing_env!(context.clone());
-        let mut contract = default_minter_init();
-        // Deposit to the current signer account
-        contract.storage_deposit(None);
-        // Attach 1 yocto_near
-        context.attached_deposit = ONE_YOCTO;
+        contract.set_pack_burn(true);
+        assert_eq!(
+            get_logs(),
+            vec![
+                r#"EVENT_JSON:{"standard":"loterra_minter","version":"1.0.0","event":"pack_burn_set","data":[{"pack_burn":true}]}"#
+            ]
+        );
+
+        context.predecessor_account_id = alice.clone();
+        context.block_timestamp = 300;
         testing_env!(context.clone());
-        // Withdraw previously deposited amount
-        contract.storage_withdraw_all();
-        // // Verify storage deposit
-        // assert_eq!(contract.get_storage_balance_of(context.clone().signer_account_id), "No account found");
+        assert!(contract.get_soft_cap().refunds_open);
+        contract.claim_refund(
+            Some(usdc.clone()),
+            Some(vec![
+                "4999".to_string(),
+                "4998".to_string(),
+                "4997".to_string(),
+            ]),
+        );
+        assert_eq!(
+            contract.get_refund(alice.clone(), Some(usdc.clone())),
+            U128::from(0)
+        );
+
+        // The purchase is restored when the NFT packs are not burned
+        context.predecessor_account_id = context.current_account_id.clone();
+        set_promise_result(context, PromiseResult::Failed);
+        contract.on_refund_packs_burned(
+            alice.clone(),
+            Some(usdc.clone()),
+            Purchase {
+                packs: 3,
+                paid: U128::from(300),
+            },
+            None,
+        );
+        assert_eq!(contract.get_refund(alice, Some(usdc)), U128::from(300));
     }
 
     #[test]
-    #[should_panic]
-    fn try_storage_deposit_withdraw_without_attaching_one_yocto() {
-        let mut context = get_context(false);
-        context.signer_account_id = AccountId::new_unchecked("alice_near".to_string());
-        // Attach minimum deposit amount to the sender
-        context.attached_deposit = U128::from(5_870_000_000_000_000_000_000).0;
+    #[should_panic(expected = "Requires 3 NFT packs to be returned")]
+    fn try_claim_refund_missing_packs() {
+        let (mut contract, mut context) = soft_cap_purchase_settled();
+        context.predecessor_account_id = "admin_near".parse::<AccountId>().unwrap();
         testing_env!(context.clone());
-        let mut contract = default_minter_init();
-        // Deposit to the current signer account
-        contract.storage_deposit(None);
-        // Withdraw previously deposited amount
-        contract.storage_withdraw_all();
+        contract.set_pack_burn(true);
+        context.predecessor_account_id = context.signer_account_id.clone();
+        context.block_timestamp = 300;
+        testing_env!(context);
+        contract.claim_refund(
+            Some("usdc_near".parse::<AccountId>().unwrap()),
+            Some(vec!["4999".to_string()]),
+        );
     }
+
     #[test]
-    #[should_panic]
-    fn try_storage_deposit_withdraw_no_deposit_found() {
-        let mut context = get_context(false);
-        context.signer_account_id = AccountId::new_unchecked("alice_near".to_string());
+    fn try_claim_refund_paid_in_near() {
+        let (mut contract, mut context) = near_price_alice_at(200);
+        let alice = context.signer_account_id.clone();
+        context.attached_deposit = NearToken::from_yoctonear(ONE_NEAR);
         testing_env!(context.clone());
-        let mut contract = default_minter_init();
-        // Attach 1 yocto_near
-        context.attached_deposit = ONE_YOCTO;
+        contract.nft_pack_buy(1, None, None);
+        set_promise_result(context.clone(), PromiseResult::Successful(vec![]));
+        contract.mint_result(
+            vec!["4999".to_string()],
+            alice.clone(),
+            U128::from(MINT_STORAGE_COST),
+            U128::from(0),
+            U128::from(ONE_NEAR),
+            U128::from(0),
+            None,
+            None,
+            None,
+        );
+
+        context.predecessor_account_id = "admin_near".parse::<AccountId>().unwrap();
+        context.attached_deposit = NearToken::from_yoctonear(0);
         testing_env!(context.clone());
-        // Withdraw previously deposited amount
-        contract.storage_withdraw_all();
+        contract.cancel_sale();
+        assert_eq!(
+            contract.get_refund(alice.clone(), None),
+            U128::from(ONE_NEAR)
+        );
+
+        context.predecessor_account_id = alice.clone();
+        testing_env!(context.clone());
+        contract.claim_refund(None, None);
+        assert_eq!(
+            get_logs(),
+            vec![
+                r#"EVENT_JSON:{"standard":"loterra_minter","version":"1.0.0","event":"refund_claim","data":[{"account_id":"alice_near","payment_token":null,"amount":"1000000000000000000000000"}]}"#
+            ]
+        );
+        assert_eq!(contract.get_refund(alice.clone(), None), U128::from(0));
+
+        // The refund is claimable again when the transfer failed
+        context.predecessor_account_id = context.current_account_id.clone();
+        set_promise_result(context, PromiseResult::Failed);
+        contract.on_refund_claimed(
+            alice.clone(),
+            None,
+            Purchase {
+                packs: 1,
+                paid: U128::from(ONE_NEAR),
+            },
+            None,
+        );
+        assert_eq!(contract.get_refund(alice, None), U128::from(ONE_NEAR));
     }
 
     #[test]
-    fn try_mint_sending_ft_sale_not_started_yet() {
+    #[should_panic(expected = "The proceeds are locked until the soft cap is reached")]
+    fn try_admin_withdraw_near_below_soft_cap() {
+        let (mut contract, mut context) = soft_cap_purchase_settled();
+        context.predecessor_account_id = "admin_near".parse::<AccountId>().unwrap();
+        context.account_balance = NearToken::from_yoctonear(100 * ONE_NEAR);
+        testing_env!(context);
+        contract.admin_withdraw_near(U128::from(ONE_NEAR));
+    }
+
+    #[test]
+    #[should_panic(expected = "Proceeds were already withdrawn")]
+    fn try_cancel_sale_after_admin_withdraw_near() {
+        let (mut contract, mut context) = whitelisted_alice_at(150, 200);
+        context.predecessor_account_id = "admin_near".parse::<AccountId>().unwrap();
+        context.account_balance = NearToken::from_yoctonear(100 * ONE_NEAR);
+        testing_env!(context);
+        contract.admin_withdraw_near(U128::from(ONE_NEAR));
+        contract.cancel_sale();
+    }
+
+    #[test]
+    fn try_mint_reserved() {
         let mut context = get_context(false);
-        context.signer_account_id = AccountId::new_unchecked("alice_near".to_string());
-        context.predecessor_account_id = AccountId::new_unchecked("usdc_near".to_string());
-        context.attached_deposit = U128::from(5_870_000_000_000_000_000_000).0;
         testing_env!(context.clone());
         let mut contract = default_minter_init();
-        // Deposit Near for minting
-        contract.storage_deposit(None);
-        contract.ft_on_transfer(
-            context.signer_account_id,
-            U128::from(1000),
-            json!({
-                "mint_amount": 10
-            })
-            .to_string(),
+        let bob = "bob_near".parse::<AccountId>().unwrap();
+        contract.set_reserved_supply(100);
+        assert_eq!(
+            get_logs()[2..],
+            vec![
+                r#"EVENT_JSON:{"standard":"loterra_minter","version":"1.0.0","event":"reserved_supply_set","data":[{"reserved_supply":100}]}"#,
+                r#"EVENT_JSON:{"standard":"loterra_minter","version":"1.0.0","event":"supply_change","data":[{"supply":4900,"pending":0}]}"#,
+            ]
+        );
+        assert_eq!(contract.get_nft_pack_supply(), 4900);
+
+        context.attached_deposit = NearToken::from_yoctonear(2 * MINT_STORAGE_COST);
+        testing_env!(context.clone());
+        contract.mint_reserved(bob.clone(), 2);
+        assert_eq!(contract.get_reserved_supply(), (98, 0));
+        assert_eq!(contract.get_pending_mints(), 2);
+
+        context.predecessor_account_id = context.current_account_id.clone();
+        set_promise_result(context, PromiseResult::Successful(vec![]));
+        contract.on_reserved_minted(
+            vec!["4999".to_string(), "4998".to_string()],
+            bob,
+            "admin_near".parse::<AccountId>().unwrap(),
+            U128::from(2 * MINT_STORAGE_COST),
+        );
+        assert_eq!(
+            get_logs(),
+            vec![
+                r#"EVENT_JSON:{"standard":"loterra_minter","version":"1.0.0","event":"reserved_mint","data":[{"receiver_id":"bob_near","token_ids":["4999","4998"]}]}"#,
+                r#"EVENT_JSON:{"standard":"loterra_minter","version":"1.0.0","event":"supply_change","data":[{"supply":4900,"pending":0}]}"#,
+            ]
         );
+        assert_eq!(contract.get_reserved_supply(), (98, 2));
+        assert_eq!(contract.get_nft_pack_supply(), 4900);
     }
 
     #[test]
-    fn try_mint_sending_ft_private_sale_started_account_whitelisted() {
+    fn try_mint_reserved_failed_rollback() {
         let mut context = get_context(false);
-        // Admin whitelist Alice
-        context.signer_account_id = AccountId::new_unchecked("admin_near".to_string());
         testing_env!(context.clone());
         let mut contract = default_minter_init();
-        contract.add_whitelist(
-            AccountId::new_unchecked("alice_near".to_string()),
-            env::block_timestamp().checked_add(100).unwrap(),
-            U128::from(1000),
-            5,
-        );
-
-        // Alice try to participate the public sale
-        context.signer_account_id = AccountId::new_unchecked("alice_near".to_string());
-        context.predecessor_account_id = AccountId::new_unchecked("usdc_near".to_string());
-        context.attached_deposit = U128::from(40_350_000_000_000_000_000_000).0;
-        context.block_timestamp = 100;
+        let bob = "bob_near".parse::<AccountId>().unwrap();
+        contract.set_reserved_supply(10);
+        context.attached_deposit = NearToken::from_yoctonear(MINT_STORAGE_COST);
         testing_env!(context.clone());
+        contract.mint_reserved(bob.clone(), 1);
 
-        // Deposit Near for minting
-        contract.storage_deposit(None);
-        // Mint 3 of 5
-        contract.ft_on_transfer(
-            context.signer_account_id.clone(),
-            U128::from(300),
-            json!({
-                "mint_amount": 3
-            })
-            .to_string(),
+        context.predecessor_account_id = context.current_account_id.clone();
+        set_promise_result(context, PromiseResult::Failed);
+        contract.on_reserved_minted(
+            vec!["4999".to_string()],
+            bob,
+            "admin_near".parse::<AccountId>().unwrap(),
+            U128::from(MINT_STORAGE_COST),
         );
-        // // Mint 5 of 5
-        contract.ft_on_transfer(
-            context.signer_account_id,
-            U128::from(200),
-            json!({
-                "mint_amount": 2
-            })
-            .to_string(),
+        assert_eq!(contract.get_reserved_supply(), (10, 0));
+        assert_eq!(contract.get_pending_mints(), 0);
+        // The token id is minted first by the next purchase
+        assert_eq!(
+            contract.internal_reserve_token_ids(1),
+            vec!["4999".to_string()]
         );
     }
 
     #[test]
-    #[should_panic]
-    fn try_mint_sending_ft_private_sale_started_account_not_whitelisted() {
+    #[should_panic(expected = "Requires minimum deposit of 16000000000000000000000 YoctoNear")]
+    fn try_mint_reserved_without_storage_deposit() {
         let mut context = get_context(false);
-        context.signer_account_id = AccountId::new_unchecked("alice_near".to_string());
-        context.predecessor_account_id = AccountId::new_unchecked("usdc_near".to_string());
-        context.attached_deposit = U128::from(5_870_000_000_000_000_000_000).0;
         testing_env!(context.clone());
         let mut contract = default_minter_init();
-        // Deposit Near for minting
-        contract.storage_deposit(None);
-        contract.ft_on_transfer(
-            context.signer_account_id,
-            U128::from(1000),
-            json!({
-                "mint_amount": 10
-            })
-            .to_string(),
-        );
+        contract.set_reserved_supply(100);
+        // The NEAR held by the minter doesn't pay the storage of the reserved mints
+        context.account_balance = NearToken::from_yoctonear(100 * ONE_NEAR);
+        context.attached_deposit = NearToken::from_yoctonear(MINT_STORAGE_COST);
+        testing_env!(context);
+        contract.mint_reserved("bob_near".parse::<AccountId>().unwrap(), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "Reserved supply limit reached. Left 0 NFT pack")]
+    fn try_mint_reserved_without_reserved_supply() {
+        let context = get_context(false);
+        testing_env!(context);
+        let mut contract = default_minter_init();
+        contract.mint_reserved("bob_near".parse::<AccountId>().unwrap(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Minting is paused")]
+    fn try_mint_reserved_paused() {
+        let context = get_context(false);
+        testing_env!(context);
+        let mut contract = default_minter_init();
+        contract.set_reserved_supply(100);
+        contract.pause(PauseScope::All);
+        contract.mint_reserved("bob_near".parse::<AccountId>().unwrap(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "Supply limit reached. Left 5000 NFT pack")]
+    fn try_set_reserved_supply_above_supply() {
+        let context = get_context(false);
+        testing_env!(context);
+        let mut contract = default_minter_init();
+        contract.set_reserved_supply(5001);
     }
 }
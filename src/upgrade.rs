@@ -0,0 +1,182 @@
+use crate::*;
+
+/*
+   The state version is stored outside of the Minter state, a missing version is the
+   state deployed before versioning (v1)
+*/
+const VERSION_KEY: &[u8] = b"VERSION";
+pub(crate) const STATE_VERSION: u8 = 2;
+const MIGRATE_GAS: u64 = 50_000_000_000_000;
+
+/// Minter state before versioning
+#[derive(BorshDeserialize, BorshSerialize)]
+#[borsh(crate = "near_sdk::borsh")]
+pub(crate) struct MinterV1 {
+    pub whitelist: UnorderedMap<AccountId, WhitelistState>,
+    pub minting_price: U128,
+    pub admin: AccountId,
+    pub usdc_account_id: AccountId,
+    pub usdt_account_id: AccountId,
+    pub private_sale_start: u64,
+    pub public_sale_start: u64,
+    pub switch_off: bool,
+    pub minted: LookupMap<AccountId, u16>,
+    pub storage_deposits: LookupMap<AccountId, U128>,
+    pub nft_pack_contract: AccountId,
+    pub nft_pack_supply: u16,
+}
+
+impl From<MinterV1> for Minter {
+    fn from(old: MinterV1) -> Self {
+        // USDC & USDT were the only accepted tokens, both are 6 decimals
+        let mut payment_tokens = UnorderedMap::new(b"p");
+        for token_account_id in [old.usdc_account_id, old.usdt_account_id] {
+            payment_tokens.insert(
+                &token_account_id,
+                &PaymentToken {
+                    decimals: 6,
+                    enabled: true,
+                    paused: false,
+                },
+            );
+        }
+
+        Self {
+            whitelist: old.whitelist,
+            tiers: UnorderedMap::new(b"t"),
+            tier_members: LookupMap::new(b"u"),
+            merkle_root: None,
+            voucher_public_key: None,
+            used_voucher_nonces: LookupSet::new(b"n"),
+            minting_price: old.minting_price,
+            admin: old.admin,
+            pending_admin: None,
+            roles: UnorderedMap::new(b"o"),
+            payment_tokens,
+            near_minting_price: None,
+            dutch_auction: None,
+            auction_purchases: LookupMap::new(b"a"),
+            auction_last_price: None,
+            clearing_price: None,
+            auction_totals: LookupMap::new(b"v"),
+            payees: vec![],
+            proceeds: LookupMap::new(b"c"),
+            payouts: LookupMap::new(b"y"),
+            payment_totals: LookupMap::new(b"k"),
+            // The v1 purchases are not tracked and can't be refunded
+            purchases: LookupMap::new(b"b"),
+            near_purchases: LookupMap::new(b"e"),
+            soft_cap: None,
+            sale_cancelled: false,
+            packs_sold: 0,
+            sale_proceeds: 0,
+            near_withdrawn: 0,
+            pack_burn: false,
+            private_sale_start: old.private_sale_start,
+            public_sale_start: old.public_sale_start,
+            public_sale_end: None,
+            switch_off: old.switch_off,
+            public_sale_paused: false,
+            minted: old.minted,
+            storage_deposits: old.storage_deposits,
+            /*
+               The v1 storage deposits can't be iterated, the whole balance held at the
+               migration is reserved until the owner sets the total summed off-chain
+            */
+            total_storage_deposits: env::account_balance().as_yoctonear(),
+            migrated_deposits: true,
+            pending_near_payments: 0,
+            mint_storage_cost: U128::from(MINT_STORAGE_COST),
+            mint_storage_usage: MINT_STORAGE_USAGE,
+            nft_pack_contract: old.nft_pack_contract,
+            // v1 only reduced the supply once minted, token ids below the supply are unused
+            nft_pack_pending: 0,
+            token_id_cursor: old.nft_pack_supply,
+            nft_pack_supply: old.nft_pack_supply,
+            released_token_ids: Vector::new(b"r"),
+            reserved_supply: 0,
+            reserved_minted: 0,
+            // Metadata used by the v1 deployment
+            pack_metadata: NFTContractMetadata {
+                spec: "nft-1.0.0".to_string(),
+                name: "test".to_string(),
+                symbol: "PACK".to_string(),
+                icon: None,
+                base_uri: None,
+                reference: None,
+                reference_hash: None,
+            },
+            pack_contract_status: PackContractStatus::Deployed,
+        }
+    }
+}
+
+pub(crate) fn write_state_version() {
+    env::storage_write(VERSION_KEY, &[STATE_VERSION]);
+}
+
+#[near_bindgen]
+impl Minter {
+    /// Upgrader deploy the new contract code passed as input and migrate the state
+    pub fn upgrade(&self) -> Promise {
+        self.assert_role(Role::Upgrader);
+        let code = env::input().expect("Missing contract code");
+        log!(format!("Upgrade contract code ({} bytes)", code.len()));
+
+        Promise::new(env::current_account_id())
+            .deploy_contract(code)
+            .function_call(
+                "migrate".to_string(),
+                json!({}).to_string().as_bytes().to_vec(),
+                NearToken::from_yoctonear(0),
+                Gas::from_gas(MIGRATE_GAS),
+            )
+    }
+
+    /// Migrate the state of a previous version to the current version
+    #[private]
+    #[init(ignore_state)]
+    pub fn migrate() -> Self {
+        let version = env::storage_read(VERSION_KEY).map_or(1, |version| version[0]);
+        let minter = match version {
+            1 => Minter::from(env::state_read::<MinterV1>().expect("No state to migrate")),
+            STATE_VERSION => env::state_read().expect("No state to migrate"),
+            _ => env::panic_str("Unknown state version"),
+        };
+        log!(format!(
+            "Migrate state from v{} to v{}",
+            version, STATE_VERSION
+        ));
+        write_state_version();
+        minter
+    }
+
+    /// Owner set once the total of the storage deposits migrated from v1, summed off-chain
+    /// from the deposits of the v1 accounts. It can only lower the balance reserved at the
+    /// migration, the NEAR released is withdrawable by the admin
+    pub fn set_migrated_storage_deposits(&mut self, total_storage_deposits: U128) {
+        self.assert_owner();
+        require!(
+            self.migrated_deposits,
+            "No migrated storage deposits to set"
+        );
+        require!(
+            total_storage_deposits.0 <= self.total_storage_deposits,
+            format!(
+                "The storage deposits can't exceed the {} YoctoNear reserved",
+                self.total_storage_deposits
+            )
+        );
+        MinterEvent::MigratedDepositsSet(vec![MigratedDepositsData {
+            total_storage_deposits,
+        }])
+        .emit();
+        self.total_storage_deposits = total_storage_deposits.0;
+        self.migrated_deposits = false;
+    }
+
+    /// Get the state version
+    pub fn get_state_version(&self) -> u8 {
+        env::storage_read(VERSION_KEY).map_or(1, |version| version[0])
+    }
+}